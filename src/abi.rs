@@ -0,0 +1,617 @@
+use anyhow::{anyhow, bail, Result};
+use ruint::aliases::U256;
+use serde_json::Value;
+use std::fmt;
+use tiny_keccak::{Hasher, Keccak};
+
+use crate::opcodes::*;
+use crate::sym_stack::Term;
+
+/// Elements of dynamic arrays decoded before the rest are elided
+const MAX_ELEMENTS: usize = 16;
+
+/// Bound on offsets and lengths of dynamic arguments, the most calldata replay lays out
+const MAX_TAIL: u64 = 1 << 16;
+
+/// Solidity ABI types that calldata arguments can be modelled as
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbiType {
+    /// `uint<bits>`
+    Uint(usize),
+    /// `int<bits>`
+    Int(usize),
+    Address,
+    Bool,
+    /// `bytes<n>`
+    FixedBytes(usize),
+    Bytes,
+    String,
+    /// `T[]`
+    Array(Box<AbiType>),
+    /// `T[k]`
+    FixedArray(Box<AbiType>, usize),
+}
+
+impl AbiType {
+    /// Parse a canonical type name such as `uint256`, `address[]` or `bytes32[2]`
+    pub fn parse(text: &str) -> Result<Self> {
+        if let Some(inner) = text.strip_suffix("[]") {
+            return Ok(AbiType::Array(Box::new(AbiType::parse(inner)?)));
+        }
+        if let Some((inner, length)) = text
+            .strip_suffix(']')
+            .and_then(|text| text.rsplit_once('['))
+        {
+            let length = length
+                .parse()
+                .map_err(|_| anyhow!("Bad array length in {}", text))?;
+            return Ok(AbiType::FixedArray(
+                Box::new(AbiType::parse(inner)?),
+                length,
+            ));
+        }
+
+        let bits = |digits: &str| -> Result<usize> {
+            match digits {
+                "" => Ok(256),
+                _ => match digits.parse() {
+                    Ok(bits) if bits % 8 == 0 && (8..=256).contains(&bits) => Ok(bits),
+                    _ => bail!("Bad integer width in {}", text),
+                },
+            }
+        };
+        let kind = match text {
+            "address" => AbiType::Address,
+            "bool" => AbiType::Bool,
+            "bytes" => AbiType::Bytes,
+            "string" => AbiType::String,
+            _ if text.starts_with("uint") => AbiType::Uint(bits(&text[4..])?),
+            _ if text.starts_with("int") => AbiType::Int(bits(&text[3..])?),
+            _ if text.starts_with("bytes") => match text[5..].parse() {
+                Ok(size) if (1..=32).contains(&size) => AbiType::FixedBytes(size),
+                _ => bail!("Bad fixed bytes size in {}", text),
+            },
+            _ => bail!("Unsupported ABI type {}", text),
+        };
+        Ok(kind)
+    }
+
+    /// Whether the value lives in the tail of the encoding, behind an offset in the head
+    pub fn is_dynamic(&self) -> bool {
+        match self {
+            AbiType::Bytes | AbiType::String | AbiType::Array(_) => true,
+            AbiType::FixedArray(inner, _) => inner.is_dynamic(),
+            _ => false,
+        }
+    }
+
+    /// Words the type takes in the head of an encoding
+    fn head_words(&self) -> usize {
+        match self {
+            AbiType::FixedArray(inner, length) if !self.is_dynamic() => inner.head_words() * length,
+            _ => 1,
+        }
+    }
+}
+
+impl fmt::Display for AbiType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AbiType::Uint(bits) => write!(f, "uint{}", bits),
+            AbiType::Int(bits) => write!(f, "int{}", bits),
+            AbiType::Address => write!(f, "address"),
+            AbiType::Bool => write!(f, "bool"),
+            AbiType::FixedBytes(size) => write!(f, "bytes{}", size),
+            AbiType::Bytes => write!(f, "bytes"),
+            AbiType::String => write!(f, "string"),
+            AbiType::Array(inner) => write!(f, "{}[]", inner),
+            AbiType::FixedArray(inner, length) => write!(f, "{}[{}]", inner, length),
+        }
+    }
+}
+
+/// A named function argument
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Param {
+    pub name: String,
+    pub kind: AbiType,
+}
+
+/// A function from the ABI
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbiFunction {
+    pub name: String,
+    pub inputs: Vec<Param>,
+}
+
+/// Functions of a contract ABI
+#[derive(Debug, Clone, Default)]
+pub struct Abi {
+    pub functions: Vec<AbiFunction>,
+    /// Functions left out because they take arguments of types we cannot model, such as tuples
+    pub skipped: Vec<String>,
+}
+
+impl Abi {
+    /// Parse an ABI JSON array, or a compiler artifact holding one under `abi`
+    pub fn parse(json: &str) -> Result<Self> {
+        let json: Value = serde_json::from_str(json)?;
+        let entries = json
+            .as_array()
+            .or_else(|| json["abi"].as_array())
+            .ok_or_else(|| anyhow!("ABI is not an array of entries"))?;
+
+        let mut abi = Abi::default();
+        for entry in entries.iter().filter(|entry| entry["type"] == "function") {
+            let name = entry["name"]
+                .as_str()
+                .ok_or_else(|| anyhow!("ABI function without a name"))?;
+            let inputs = entry["inputs"]
+                .as_array()
+                .map(|inputs| inputs.as_slice())
+                .unwrap_or_default()
+                .iter()
+                .map(|input| {
+                    Ok(Param {
+                        name: input["name"].as_str().unwrap_or_default().to_string(),
+                        kind: AbiType::parse(input["type"].as_str().unwrap_or_default())?,
+                    })
+                })
+                .collect::<Result<Vec<Param>>>();
+            match inputs {
+                Ok(inputs) => abi.functions.push(AbiFunction {
+                    name: name.to_string(),
+                    inputs,
+                }),
+                Err(_) => abi.skipped.push(name.to_string()),
+            }
+        }
+        Ok(abi)
+    }
+
+    /// The function a selector calls
+    pub fn function(&self, selector: [u8; 4]) -> Option<&AbiFunction> {
+        self.functions
+            .iter()
+            .find(|function| function.selector() == selector)
+    }
+
+    /// The function with this signature, or this name when it is not overloaded
+    pub fn find(&self, name: &str) -> Option<&AbiFunction> {
+        let by_signature = self
+            .functions
+            .iter()
+            .find(|function| function.signature() == name);
+        let mut by_name = self
+            .functions
+            .iter()
+            .filter(|function| function.name == name);
+        by_signature.or_else(|| match (by_name.next(), by_name.next()) {
+            (Some(function), None) => Some(function),
+            _ => None,
+        })
+    }
+
+    /// Calldata decoded as a call such as `transfer(to=0x..01, amount=11)`, when its selector is
+    /// in the ABI
+    pub fn decode_call(&self, calldata: &[u8]) -> Option<String> {
+        let selector: [u8; 4] = calldata.get(..4)?.try_into().ok()?;
+        Some(self.function(selector)?.decode(calldata))
+    }
+}
+
+impl AbiFunction {
+    /// Canonical signature the selector is hashed from, e.g. `transfer(address,uint256)`
+    pub fn signature(&self) -> String {
+        let types: Vec<String> = self
+            .inputs
+            .iter()
+            .map(|input| input.kind.to_string())
+            .collect();
+        format!("{}({})", self.name, types.join(","))
+    }
+
+    pub fn selector(&self) -> [u8; 4] {
+        let mut hasher = Keccak::v256();
+        let mut hash = [0u8; 32];
+        hasher.update(self.signature().as_bytes());
+        hasher.finalize(&mut hash);
+        [hash[0], hash[1], hash[2], hash[3]]
+    }
+
+    /// Range constraints on the calldata words holding the arguments, as conditions that are
+    /// non-zero when the words are well formed: narrow integers and addresses have their high
+    /// bits clear and bools are 0 or 1. Offsets of dynamic arguments are word aligned and point
+    /// past the head, so the tail never aliases it. Offsets and the lengths they point at stay below
+    /// `MAX_TAIL`, so witnesses fit in calldata that replay can build.
+    /// A length is read at `4 + offset`, which decoders add up in either order.
+    pub fn assumptions(&self) -> Vec<Term> {
+        let head_size: usize = self
+            .inputs
+            .iter()
+            .map(|input| 32 * input.kind.head_words())
+            .sum();
+        let mut assumptions = Vec::new();
+        let mut offset = 4;
+        for input in &self.inputs {
+            static_assumptions(&input.kind, offset, &mut assumptions);
+            if input.kind.is_dynamic() {
+                let word = calldata_word(offset);
+                assumptions.push(Term::op(
+                    ISZERO,
+                    vec![Term::op(
+                        LT,
+                        vec![word.clone(), Term::constant(head_size as u64)],
+                    )],
+                ));
+                assumptions.push(Term::op(
+                    ISZERO,
+                    vec![Term::op(AND, vec![word.clone(), Term::constant(31)])],
+                ));
+                for position in [
+                    Term::op(ADD, vec![Term::constant(4), word.clone()]),
+                    Term::op(ADD, vec![word.clone(), Term::constant(4)]),
+                ] {
+                    assumptions.push(Term::op(
+                        LT,
+                        vec![
+                            Term::op(CALLDATALOAD, vec![position]),
+                            Term::constant(MAX_TAIL),
+                        ],
+                    ));
+                }
+            }
+            offset += 32 * input.kind.head_words();
+        }
+        assumptions
+    }
+
+    /// Extend calldata to a complete encoding of the call: the whole head, and the tail each
+    /// dynamic argument's offset and length reach. The bytes added are zero, which is what the
+    /// code read past the end of the calldata.
+    pub fn complete(&self, calldata: &mut Vec<u8>) {
+        let data = calldata.get(4..).unwrap_or_default();
+        let mut head = 0;
+        let mut end = 0;
+        for input in &self.inputs {
+            if let Some(tail_end) = tail_end(&input.kind, data, head) {
+                end = end.max(tail_end);
+            }
+            head += 32 * input.kind.head_words();
+        }
+        let end = 4 + end.max(head);
+        if calldata.len() < end {
+            calldata.resize(end, 0);
+        }
+    }
+
+    /// Arguments decoded from calldata as `name(arg=value, ...)`. Calldata shorter than the
+    /// encoding reads as zeros.
+    pub fn decode(&self, calldata: &[u8]) -> String {
+        let data = calldata.get(4..).unwrap_or_default();
+        let mut head = 0;
+        let mut args = Vec::new();
+        for (i, input) in self.inputs.iter().enumerate() {
+            let name = match input.name.as_str() {
+                "" => format!("arg{}", i),
+                name => name.to_string(),
+            };
+            args.push(format!(
+                "{}={}",
+                name,
+                decode_value(&input.kind, data, head)
+            ));
+            head += 32 * input.kind.head_words();
+        }
+        format!("{}({})", self.name, args.join(", "))
+    }
+}
+
+fn calldata_word(offset: usize) -> Term {
+    Term::op(CALLDATALOAD, vec![Term::constant(offset as u64)])
+}
+
+fn static_assumptions(kind: &AbiType, offset: usize, assumptions: &mut Vec<Term>) {
+    let word = calldata_word(offset);
+    let high_bits_clear = |bits: usize| {
+        Term::op(
+            ISZERO,
+            vec![Term::op(
+                SHR,
+                vec![Term::constant(bits as u64), word.clone()],
+            )],
+        )
+    };
+    let assumption = match kind {
+        AbiType::Uint(bits) if *bits < 256 => high_bits_clear(*bits),
+        AbiType::Address => high_bits_clear(160),
+        // Sign extending from the top byte leaves a well formed value unchanged
+        AbiType::Int(bits) if *bits < 256 => Term::op(
+            EQ,
+            vec![
+                Term::op(
+                    SIGNEXTEND,
+                    vec![Term::constant((bits / 8 - 1) as u64), word.clone()],
+                ),
+                word,
+            ],
+        ),
+        AbiType::Bool => Term::op(LT, vec![word, Term::constant(2)]),
+        // Fixed bytes are left aligned, the bytes past them are zero
+        AbiType::FixedBytes(size) if *size < 32 => Term::op(
+            ISZERO,
+            vec![Term::op(SHL, vec![Term::constant(8 * *size as u64), word])],
+        ),
+        AbiType::FixedArray(inner, length) if !kind.is_dynamic() => {
+            for i in 0..*length {
+                static_assumptions(inner, offset + 32 * inner.head_words() * i, assumptions);
+            }
+            return;
+        }
+        _ if kind.is_dynamic() => Term::op(LT, vec![word, Term::constant(MAX_TAIL)]),
+        _ => return,
+    };
+    assumptions.push(assumption);
+}
+
+// End of the tail a dynamic argument with its head at `head` occupies, relative to the start of
+// `data`, when its offset and length are within `MAX_TAIL`. Tails of nested dynamic elements
+// are not followed.
+fn tail_end(kind: &AbiType, data: &[u8], head: usize) -> Option<usize> {
+    if !kind.is_dynamic() {
+        return None;
+    }
+    let bounded = |word: U256| match u64::try_from(word) {
+        Ok(value) if value < MAX_TAIL => Some(value as usize),
+        _ => None,
+    };
+    let offset = bounded(word_at(data, head))?;
+    let length = || bounded(word_at(data, offset));
+    let size = match kind {
+        AbiType::Bytes | AbiType::String => 32 + length()?.div_ceil(32) * 32,
+        AbiType::Array(inner) => 32 + length()? * 32 * inner.head_words(),
+        AbiType::FixedArray(inner, length) => length * 32 * inner.head_words(),
+        _ => return None,
+    };
+    Some(offset + size)
+}
+
+// Word at `position`, with bytes past the end of the data reading as zero
+fn word_at(data: &[u8], position: usize) -> U256 {
+    let mut word = [0u8; 32];
+    for (i, byte) in word.iter_mut().enumerate() {
+        *byte = position
+            .checked_add(i)
+            .and_then(|index| data.get(index))
+            .copied()
+            .unwrap_or(0);
+    }
+    U256::from_be_bytes(word)
+}
+
+// Slice of `length` bytes at `position`, zero padded past the end of the data
+fn bytes_at(data: &[u8], position: usize, length: usize) -> Vec<u8> {
+    (0..length)
+        .map(|i| data.get(position + i).copied().unwrap_or(0))
+        .collect()
+}
+
+// Value of the argument whose head is at `head`, with offsets relative to the start of `data`
+fn decode_value(kind: &AbiType, data: &[u8], head: usize) -> String {
+    let word = word_at(data, head);
+    if !kind.is_dynamic() {
+        return match kind {
+            AbiType::Uint(_) => word.to_string(),
+            AbiType::Int(_) if word.bit(255) => format!("-{}", word.wrapping_neg()),
+            AbiType::Int(_) => word.to_string(),
+            AbiType::Address => format!("0x{}", hex::encode(&word.to_be_bytes::<32>()[12..])),
+            AbiType::Bool if word <= U256::from(1) => (!word.is_zero()).to_string(),
+            AbiType::Bool => format!("{:#x}", word),
+            AbiType::FixedBytes(size) => {
+                format!("0x{}", hex::encode(&word.to_be_bytes::<32>()[..*size]))
+            }
+            AbiType::FixedArray(inner, length) => {
+                let elements: Vec<String> = (0..*length)
+                    .map(|i| decode_value(inner, data, head + 32 * inner.head_words() * i))
+                    .collect();
+                format!("[{}]", elements.join(", "))
+            }
+            _ => unreachable!("dynamic types are decoded below"),
+        };
+    }
+
+    // Offsets and lengths are only meaningful while they point into the data
+    let offset = match usize::try_from(word) {
+        Ok(offset) if offset <= data.len() => offset,
+        _ => return format!("<offset {:#x} out of range>", word),
+    };
+    let tail = &data[offset..];
+    let length = usize::try_from(word_at(tail, 0)).unwrap_or(usize::MAX);
+    let elements = |inner: &AbiType, base: &[u8], length: usize| {
+        let mut elements: Vec<String> = (0..length.min(MAX_ELEMENTS))
+            .map(|i| decode_value(inner, base, 32 * inner.head_words() * i))
+            .collect();
+        if length > MAX_ELEMENTS {
+            elements.push(format!("... {} more", length - MAX_ELEMENTS));
+        }
+        format!("[{}]", elements.join(", "))
+    };
+    match kind {
+        AbiType::Bytes | AbiType::String if length > tail.len() => {
+            format!("<length {:#x} out of range>", word_at(tail, 0))
+        }
+        AbiType::Bytes => format!("0x{}", hex::encode(bytes_at(tail, 32, length))),
+        AbiType::String => format!("{:?}", String::from_utf8_lossy(&bytes_at(tail, 32, length))),
+        AbiType::Array(inner) => elements(inner, tail.get(32..).unwrap_or_default(), length),
+        AbiType::FixedArray(inner, length) => elements(inner, tail, *length),
+        _ => unreachable!("static types are decoded above"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concrete::arithmetic;
+    use crate::sym_stack::is_concrete;
+
+    const ERC20: &str = r#"[
+        {"type": "function", "name": "transfer", "inputs": [
+            {"name": "to", "type": "address"}, {"name": "amount", "type": "uint256"}]},
+        {"type": "function", "name": "setName", "inputs": [{"name": "name", "type": "string"}]},
+        {"type": "function", "name": "take", "inputs": [
+            {"name": "order", "type": "tuple", "components": []}]},
+        {"type": "event", "name": "Transfer", "inputs": []}
+    ]"#;
+
+    fn word(value: u64) -> [u8; 32] {
+        U256::from(value).to_be_bytes()
+    }
+
+    // Whether an assumption holds on concrete calldata
+    fn holds(term: &Term, calldata: &[u8]) -> bool {
+        fn value(term: &Term, calldata: &[u8]) -> U256 {
+            if is_concrete(term) {
+                return U256::from(term.sym_val.value);
+            }
+            let args: Vec<U256> = term.args.iter().map(|arg| value(arg, calldata)).collect();
+            match term.sym_val.value as u8 {
+                CALLDATALOAD => word_at(calldata, args[0].to::<usize>()),
+                opcode => arithmetic(opcode, &args).unwrap(),
+            }
+        }
+        !value(term, calldata).is_zero()
+    }
+
+    #[test]
+    fn types_parse_and_print_canonically() {
+        for name in [
+            "uint256", "int8", "address", "bool", "bytes4", "bytes", "string",
+        ] {
+            assert_eq!(AbiType::parse(name).unwrap().to_string(), name);
+        }
+        assert_eq!(AbiType::parse("uint").unwrap(), AbiType::Uint(256));
+        let nested = AbiType::parse("uint8[2][]").unwrap();
+        assert_eq!(nested.to_string(), "uint8[2][]");
+        assert!(nested.is_dynamic());
+        assert!(!AbiType::parse("bytes32[3]").unwrap().is_dynamic());
+        for bad in ["uint7", "uint264", "bytes33", "tuple", "int[x]"] {
+            assert!(AbiType::parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn functions_are_found_by_selector_signature_or_name() {
+        let abi = Abi::parse(ERC20).unwrap();
+        assert_eq!(abi.functions.len(), 2);
+        assert_eq!(abi.skipped, vec!["take".to_string()]);
+
+        let transfer = abi.find("transfer").unwrap();
+        assert_eq!(transfer.signature(), "transfer(address,uint256)");
+        assert_eq!(transfer.selector(), [0xa9, 0x05, 0x9c, 0xbb]);
+        assert_eq!(abi.function([0xa9, 0x05, 0x9c, 0xbb]), Some(transfer));
+        assert_eq!(abi.find("setName(string)").unwrap().name, "setName");
+
+        let artifact = format!(r#"{{"abi": {}}}"#, ERC20);
+        assert_eq!(Abi::parse(&artifact).unwrap().functions.len(), 2);
+    }
+
+    #[test]
+    fn calls_decode_static_and_dynamic_arguments() {
+        let abi = Abi::parse(ERC20).unwrap();
+        let mut calldata = vec![0xa9, 0x05, 0x9c, 0xbb];
+        calldata.extend(word(1));
+        calldata.extend(word(11));
+        assert_eq!(
+            abi.decode_call(&calldata).unwrap(),
+            "transfer(to=0x0000000000000000000000000000000000000001, amount=11)"
+        );
+
+        let set_name = abi.find("setName").unwrap();
+        let mut calldata = set_name.selector().to_vec();
+        calldata.extend(word(0x20));
+        calldata.extend(word(2));
+        calldata.extend(b"hi");
+        assert_eq!(set_name.decode(&calldata), r#"setName(name="hi")"#);
+
+        // The length read from the tail reaches past the data
+        calldata[4 + 63] = 0xff;
+        assert_eq!(
+            set_name.decode(&calldata),
+            "setName(name=<length 0xff out of range>)"
+        );
+        assert_eq!(abi.decode_call(&[0, 0, 0, 0]), None);
+    }
+
+    #[test]
+    fn complete_pads_to_the_whole_encoding() {
+        let abi = Abi::parse(ERC20).unwrap();
+        let transfer = abi.find("transfer").unwrap();
+        let mut calldata = transfer.selector().to_vec();
+        transfer.complete(&mut calldata);
+        assert_eq!(calldata.len(), 4 + 64);
+
+        let set_name = abi.find("setName").unwrap();
+        let mut calldata = set_name.selector().to_vec();
+        calldata.extend(word(0x20));
+        calldata.extend(word(40));
+        set_name.complete(&mut calldata);
+        // Head, length word and two words of string
+        assert_eq!(calldata.len(), 4 + 32 + 32 + 64);
+    }
+
+    #[test]
+    fn assumptions_hold_exactly_on_well_formed_words() {
+        let function = AbiFunction {
+            name: "f".to_string(),
+            inputs: ["uint8", "int8", "bool", "bytes2"]
+                .iter()
+                .map(|kind| Param {
+                    name: String::new(),
+                    kind: AbiType::parse(kind).unwrap(),
+                })
+                .collect(),
+        };
+        let assumptions = function.assumptions();
+        let calldata = |words: [U256; 4]| -> Vec<u8> {
+            let mut calldata = function.selector().to_vec();
+            for word in words {
+                calldata.extend(word.to_be_bytes::<32>());
+            }
+            calldata
+        };
+        let minus_one = U256::MAX;
+        let bytes2 = U256::from(0xabcd) << 240;
+        let good = calldata([U256::from(255), minus_one, U256::from(1), bytes2]);
+        assert!(assumptions.iter().all(|term| holds(term, &good)));
+
+        let bad = [
+            calldata([U256::from(256), minus_one, U256::from(1), bytes2]),
+            calldata([U256::ZERO, U256::from(128), U256::from(1), bytes2]),
+            calldata([U256::ZERO, U256::ZERO, U256::from(2), bytes2]),
+            calldata([U256::ZERO, U256::ZERO, U256::ZERO, bytes2 | U256::from(1)]),
+        ];
+        for calldata in bad {
+            assert!(!assumptions.iter().all(|term| holds(term, &calldata)));
+        }
+    }
+
+    #[test]
+    fn dynamic_offsets_point_past_the_head() {
+        let abi = Abi::parse(ERC20).unwrap();
+        let set_name = abi.find("setName").unwrap();
+        let assumptions = set_name.assumptions();
+        let calldata = |offset: u64| {
+            let mut calldata = set_name.selector().to_vec();
+            calldata.extend(word(offset));
+            calldata.extend(word(0));
+            calldata
+        };
+        assert!(assumptions.iter().all(|term| holds(term, &calldata(0x20))));
+        // Into the head, unaligned, and past the bound
+        for offset in [0, 0x21, MAX_TAIL] {
+            assert!(!assumptions
+                .iter()
+                .all(|term| holds(term, &calldata(offset))));
+        }
+    }
+}
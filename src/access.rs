@@ -0,0 +1,166 @@
+use crate::explorer::PathResult;
+use crate::opcodes::*;
+use crate::sym_stack::{is_concrete, mentions, Term};
+
+/// Whether a condition authenticates the sender: it compares `msg.sender` or `tx.origin` with a
+/// stored value, like an owner slot, or with a fixed non-zero address, like an immutable owner.
+/// Conditions that only look at the sender, such as `msg.sender != 0`, do not count.
+pub fn checks_sender(condition: &Term) -> bool {
+    let sender = mentions(condition, CALLER) || mentions(condition, ORIGIN);
+    sender && (mentions(condition, SLOAD) || compares_to_address(condition))
+}
+
+// An EQ between the sender and a non-zero constant somewhere in the condition
+fn compares_to_address(term: &Term) -> bool {
+    if term.sym_val.value as u8 == EQ && !is_concrete(term) {
+        if let [a, b] = term.args.as_slice() {
+            let fixed = |x: &Term, y: &Term| {
+                is_concrete(x)
+                    && x.sym_val.value != 0
+                    && (mentions(y, CALLER) || mentions(y, ORIGIN))
+            };
+            if fixed(a, b) || fixed(b, a) {
+                return true;
+            }
+        }
+    }
+    !is_concrete(term) && term.args.iter().any(compares_to_address)
+}
+
+/// What `tx.origin` is compared with in a condition, one description per comparison, such as
+/// `storage slot 0x0` or `constant 0x1234`. Comparisons with `msg.sender`, the usual check that the
+/// sender is not a contract, are not authentication and are skipped.
+pub fn origin_comparisons(condition: &Term) -> Vec<String> {
+    let mut found = Vec::new();
+    collect_origin_comparisons(condition, &mut found);
+    found
+}
+
+fn collect_origin_comparisons(term: &Term, found: &mut Vec<String>) {
+    if is_concrete(term) {
+        return;
+    }
+    if term.sym_val.value as u8 == EQ {
+        if let [a, b] = term.args.as_slice() {
+            let other = match (mentions(a, ORIGIN), mentions(b, ORIGIN)) {
+                (true, false) => Some(b),
+                (false, true) => Some(a),
+                _ => None,
+            };
+            if let Some(other) = other.filter(|other| !mentions(other, CALLER)) {
+                let description = describe_value(other);
+                if !found.contains(&description) {
+                    found.push(description);
+                }
+                return;
+            }
+        }
+    }
+    for arg in &term.args {
+        collect_origin_comparisons(arg, found);
+    }
+}
+
+// Where a compared value comes from: a storage slot, a constant, or the term itself
+fn describe_value(term: &Term) -> String {
+    if is_concrete(term) {
+        return format!("constant {}", term);
+    }
+    match stored_slot(term) {
+        Some(slot) => format!("storage slot {}", slot),
+        None => term.to_string(),
+    }
+}
+
+// Key of the first SLOAD in a term, looking through masks and other arithmetic
+fn stored_slot(term: &Term) -> Option<&Term> {
+    if is_concrete(term) {
+        return None;
+    }
+    if term.sym_val.value as u8 == SLOAD {
+        return term.args.first();
+    }
+    term.args.iter().find_map(stored_slot)
+}
+
+/// Whether any sender can take the path: none of its JUMPIs authenticates the sender
+pub fn any_sender(result: &PathResult) -> bool {
+    !result
+        .branches
+        .iter()
+        .any(|branch| checks_sender(&branch.condition))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sym_stack::{Kind, SymVal};
+
+    fn constant(value: u64) -> Term {
+        Term {
+            sym_val: SymVal {
+                value,
+                kind: Kind::Concrete,
+            },
+            args: Vec::new(),
+        }
+    }
+
+    fn op(opcode: u8, args: Vec<Term>) -> Term {
+        Term {
+            sym_val: SymVal {
+                value: opcode as u64,
+                kind: Kind::Symbolic,
+            },
+            args,
+        }
+    }
+
+    fn eq(a: Term, b: Term) -> Term {
+        op(EQ, vec![a, b])
+    }
+
+    fn read(opcode: u8) -> Term {
+        op(opcode, Vec::new())
+    }
+
+    fn owner() -> Term {
+        op(SLOAD, vec![constant(0)])
+    }
+
+    #[test]
+    fn owner_checks_authenticate_the_sender() {
+        assert!(checks_sender(&eq(read(CALLER), owner())));
+        assert!(checks_sender(&eq(constant(0x1234), read(ORIGIN))));
+        // Under a mask and a negation
+        let masked = op(AND, vec![constant(0xffff), read(CALLER)]);
+        let check = op(ISZERO, vec![eq(masked, constant(0x1234))]);
+        assert!(checks_sender(&check));
+    }
+
+    #[test]
+    fn looking_at_the_sender_is_not_authentication() {
+        assert!(!checks_sender(&eq(read(CALLER), constant(0))));
+        assert!(!checks_sender(&op(ISZERO, vec![read(CALLER)])));
+        assert!(!checks_sender(&eq(read(CALLVALUE), owner())));
+    }
+
+    #[test]
+    fn origin_comparisons_describe_the_other_side() {
+        let masked_owner = op(AND, vec![constant(0xff), owner()]);
+        let condition = op(
+            AND,
+            vec![
+                eq(read(ORIGIN), masked_owner.clone()),
+                eq(masked_owner, read(ORIGIN)),
+            ],
+        );
+        assert_eq!(origin_comparisons(&condition), vec!["storage slot 0x0"]);
+        assert_eq!(
+            origin_comparisons(&eq(constant(0x1234), read(ORIGIN))),
+            vec!["constant 0x1234"]
+        );
+        // tx.origin == msg.sender only tells whether the sender is a contract
+        assert!(origin_comparisons(&eq(read(ORIGIN), read(CALLER))).is_empty());
+    }
+}
@@ -0,0 +1,39 @@
+//! Tiny stand-in for an SMT-LIB2 solver, to exercise the external solver backend without a real
+//! solver installed. Answers every `(check-sat)` with its first argument, or `$STUB_SOLVER_RESULT`
+//! (default `sat`), and every `(get-value ...)` with zero for each requested symbol. Given `die`,
+//! it exits at the first `(check-sat)` without answering, like a crashing solver, and given `hang`
+//! it never answers, like a solver stuck on a hard query.
+//!
+//! cargo build --bin stub_solver
+//! cargo run -- test.bin --solver smt:target/debug/stub_solver
+use std::io::{self, BufRead, Write};
+
+fn main() {
+    let result = std::env::args()
+        .nth(1)
+        .or_else(|| std::env::var("STUB_SOLVER_RESULT").ok())
+        .unwrap_or_else(|| "sat".to_string());
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    for line in io::stdin().lock().lines() {
+        let line = line.expect("read stdin");
+        let command = line.trim();
+        if command == "(exit)" || command == "(check-sat)" && result == "die" {
+            break;
+        } else if command == "(check-sat)" && result == "hang" {
+            continue;
+        } else if command == "(check-sat)" {
+            writeln!(out, "{}", result).unwrap();
+        } else if command.starts_with("(get-value") {
+            let values: Vec<String> = command
+                .split('|')
+                .skip(1)
+                .step_by(2)
+                .map(|name| format!("(|{}| #x{:064x})", name, 0))
+                .collect();
+            writeln!(out, "({})", values.join(" ")).unwrap();
+        }
+        out.flush().unwrap();
+    }
+}
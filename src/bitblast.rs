@@ -0,0 +1,700 @@
+use ruint::aliases::U256;
+use std::collections::HashMap;
+
+use crate::handlers::Branch;
+use crate::opcodes::*;
+use crate::sat::{Lit, SatResult, SatSolver};
+use crate::solve::{DLResult, Model, Solver};
+use crate::sym_stack::{is_concrete, Term};
+
+/// A bit-vector, least significant bit first
+type Bits = Vec<Lit>;
+
+/// Translates terms into CNF over a SAT solver, one literal per bit. Words are `width` bits wide,
+/// so narrower widths trade exactness for speed.
+pub struct BitBlaster {
+    pub sat: SatSolver,
+    width: usize,
+    true_lit: Lit,
+    /// Bits of the free variables, keyed by the term they stand for
+    pub vars: HashMap<Term, Bits>,
+    cache: HashMap<Term, Bits>,
+}
+
+impl BitBlaster {
+    pub fn new(width: usize) -> Self {
+        assert!(
+            width.is_power_of_two() && (8..=256).contains(&width),
+            "Bit-vector width must be a power of two between 8 and 256"
+        );
+        let mut sat = SatSolver::new();
+        let true_lit = Lit::new(sat.new_var(), false);
+        sat.add_clause(&[true_lit]);
+        Self {
+            sat,
+            width,
+            true_lit,
+            vars: HashMap::new(),
+            cache: HashMap::new(),
+        }
+    }
+
+    fn lit_false(&self) -> Lit {
+        !self.true_lit
+    }
+
+    fn fresh(&mut self) -> Lit {
+        Lit::new(self.sat.new_var(), false)
+    }
+
+    fn is_const(&self, lit: Lit) -> Option<bool> {
+        if lit == self.true_lit {
+            Some(true)
+        } else if lit == !self.true_lit {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    // Gates, folding constants and trivial cases before adding clauses
+
+    fn and(&mut self, a: Lit, b: Lit) -> Lit {
+        match (self.is_const(a), self.is_const(b)) {
+            (Some(false), _) | (_, Some(false)) => return self.lit_false(),
+            (Some(true), _) => return b,
+            (_, Some(true)) => return a,
+            _ => {}
+        }
+        if a == b {
+            return a;
+        }
+        if a == !b {
+            return self.lit_false();
+        }
+        let x = self.fresh();
+        self.sat.add_clause(&[!x, a]);
+        self.sat.add_clause(&[!x, b]);
+        self.sat.add_clause(&[x, !a, !b]);
+        x
+    }
+
+    fn or(&mut self, a: Lit, b: Lit) -> Lit {
+        !self.and(!a, !b)
+    }
+
+    fn xor(&mut self, a: Lit, b: Lit) -> Lit {
+        match (self.is_const(a), self.is_const(b)) {
+            (Some(false), _) => return b,
+            (_, Some(false)) => return a,
+            (Some(true), _) => return !b,
+            (_, Some(true)) => return !a,
+            _ => {}
+        }
+        if a == b {
+            return self.lit_false();
+        }
+        if a == !b {
+            return self.true_lit;
+        }
+        let x = self.fresh();
+        self.sat.add_clause(&[!x, a, b]);
+        self.sat.add_clause(&[!x, !a, !b]);
+        self.sat.add_clause(&[x, !a, b]);
+        self.sat.add_clause(&[x, a, !b]);
+        x
+    }
+
+    /// if s then t else e
+    fn mux(&mut self, s: Lit, t: Lit, e: Lit) -> Lit {
+        match self.is_const(s) {
+            Some(true) => return t,
+            Some(false) => return e,
+            None => {}
+        }
+        if t == e {
+            return t;
+        }
+        let x = self.fresh();
+        self.sat.add_clause(&[!s, !t, x]);
+        self.sat.add_clause(&[!s, t, !x]);
+        self.sat.add_clause(&[s, !e, x]);
+        self.sat.add_clause(&[s, e, !x]);
+        x
+    }
+
+    fn or_all(&mut self, lits: &[Lit]) -> Lit {
+        let mut acc = self.lit_false();
+        for &lit in lits {
+            acc = self.or(acc, lit);
+        }
+        acc
+    }
+
+    // Word level operations
+
+    fn constant(&self, value: U256, width: usize) -> Bits {
+        (0..width)
+            .map(|i| {
+                if value.bit(i) {
+                    self.true_lit
+                } else {
+                    self.lit_false()
+                }
+            })
+            .collect()
+    }
+
+    fn zero(&self, width: usize) -> Bits {
+        vec![self.lit_false(); width]
+    }
+
+    /// A boolean as a word: 1 or 0
+    fn bool_word(&self, b: Lit) -> Bits {
+        let mut bits = self.zero(self.width);
+        bits[0] = b;
+        bits
+    }
+
+    fn not_bits(&self, a: &[Lit]) -> Bits {
+        a.iter().map(|&lit| !lit).collect()
+    }
+
+    fn bitwise(&mut self, a: &[Lit], b: &[Lit], gate: fn(&mut Self, Lit, Lit) -> Lit) -> Bits {
+        a.iter().zip(b).map(|(&x, &y)| gate(self, x, y)).collect()
+    }
+
+    fn mux_bits(&mut self, s: Lit, t: &[Lit], e: &[Lit]) -> Bits {
+        t.iter().zip(e).map(|(&x, &y)| self.mux(s, x, y)).collect()
+    }
+
+    /// Ripple carry addition, returns the sum and the carry out
+    fn add_carry(&mut self, a: &[Lit], b: &[Lit], carry_in: Lit) -> (Bits, Lit) {
+        let mut carry = carry_in;
+        let mut sum = Vec::with_capacity(a.len());
+        for (&x, &y) in a.iter().zip(b) {
+            let half = self.xor(x, y);
+            sum.push(self.xor(half, carry));
+            let both = self.and(x, y);
+            let propagated = self.and(half, carry);
+            carry = self.or(both, propagated);
+        }
+        (sum, carry)
+    }
+
+    fn add(&mut self, a: &[Lit], b: &[Lit]) -> Bits {
+        let f = self.lit_false();
+        self.add_carry(a, b, f).0
+    }
+
+    fn sub(&mut self, a: &[Lit], b: &[Lit]) -> Bits {
+        let not_b = self.not_bits(b);
+        let t = self.true_lit;
+        self.add_carry(a, &not_b, t).0
+    }
+
+    fn neg(&mut self, a: &[Lit]) -> Bits {
+        let zero = self.zero(a.len());
+        self.sub(&zero, a)
+    }
+
+    /// Unsigned a < b: a - b borrows, i.e. there is no carry out of a + !b + 1
+    fn ult(&mut self, a: &[Lit], b: &[Lit]) -> Lit {
+        let not_b = self.not_bits(b);
+        let t = self.true_lit;
+        !self.add_carry(a, &not_b, t).1
+    }
+
+    fn slt(&mut self, a: &[Lit], b: &[Lit]) -> Lit {
+        // Flipping the sign bits turns a signed comparison into an unsigned one
+        let mut a = a.to_vec();
+        let mut b = b.to_vec();
+        let top = a.len() - 1;
+        a[top] = !a[top];
+        b[top] = !b[top];
+        self.ult(&a, &b)
+    }
+
+    fn eq(&mut self, a: &[Lit], b: &[Lit]) -> Lit {
+        let diffs = self.bitwise(a, b, Self::xor);
+        !self.or_all(&diffs)
+    }
+
+    fn is_zero(&mut self, a: &[Lit]) -> Lit {
+        !self.or_all(a)
+    }
+
+    fn mul(&mut self, a: &[Lit], b: &[Lit]) -> Bits {
+        let width = a.len();
+        let mut acc = self.zero(width);
+        for i in 0..width {
+            if self.is_const(b[i]) == Some(false) {
+                continue;
+            }
+            let mut partial = self.zero(width);
+            for j in 0..width - i {
+                partial[i + j] = self.and(a[j], b[i]);
+            }
+            acc = self.add(&acc, &partial);
+        }
+        acc
+    }
+
+    /// Restoring division, returns (quotient, remainder). Division by zero is left to the caller.
+    fn udivrem(&mut self, a: &[Lit], b: &[Lit]) -> (Bits, Bits) {
+        let width = a.len();
+        let f = self.lit_false();
+        let mut b_wide = b.to_vec();
+        b_wide.push(f);
+        let mut remainder = self.zero(width + 1);
+        let mut quotient = self.zero(width);
+        for i in (0..width).rev() {
+            // remainder = remainder << 1 | a[i]
+            remainder.pop();
+            remainder.insert(0, a[i]);
+            let less = self.ult(&remainder, &b_wide);
+            quotient[i] = !less;
+            let reduced = self.sub(&remainder, &b_wide);
+            remainder = self.mux_bits(less, &remainder, &reduced);
+        }
+        remainder.pop();
+        (quotient, remainder)
+    }
+
+    /// EVM semantics: anything divided by zero is zero
+    fn guard_zero(&mut self, divisor: &[Lit], result: &[Lit]) -> Bits {
+        let zero_divisor = self.is_zero(divisor);
+        let zero = self.zero(result.len());
+        self.mux_bits(zero_divisor, &zero, result)
+    }
+
+    fn abs(&mut self, a: &[Lit]) -> Bits {
+        let negated = self.neg(a);
+        self.mux_bits(a[a.len() - 1], &negated, a)
+    }
+
+    /// Barrel shifter. `left` shifts towards the most significant bit, `fill` is shifted in.
+    fn shift(&mut self, value: &[Lit], amount: &[Lit], left: bool, fill: Lit) -> Bits {
+        let width = value.len();
+        let stages = width.trailing_zeros() as usize;
+        let mut result = value.to_vec();
+        for (stage, &bit) in amount.iter().enumerate().take(stages) {
+            let step = 1 << stage;
+            let shifted: Bits = (0..width)
+                .map(|i| {
+                    if left {
+                        if i >= step {
+                            result[i - step]
+                        } else {
+                            fill
+                        }
+                    } else if i + step < width {
+                        result[i + step]
+                    } else {
+                        fill
+                    }
+                })
+                .collect();
+            result = self.mux_bits(bit, &shifted, &result);
+        }
+        // Shifting by the width or more leaves only the fill
+        let overflow = self.or_all(&amount[stages..]);
+        let filled = vec![fill; width];
+        self.mux_bits(overflow, &filled, &result)
+    }
+
+    fn zero_extend(&self, a: &[Lit], width: usize) -> Bits {
+        let mut wide = a.to_vec();
+        wide.resize(width, self.lit_false());
+        wide
+    }
+
+    /// Bits of a term, as a word of the configured width
+    pub fn blast(&mut self, term: &Term) -> Bits {
+        if let Some(bits) = self.cache.get(term) {
+            return bits.clone();
+        }
+        let bits = self.blast_uncached(term);
+        self.cache.insert(term.clone(), bits.clone());
+        bits
+    }
+
+    fn blast_uncached(&mut self, term: &Term) -> Bits {
+        if is_concrete(term) {
+            return self.constant(U256::from(term.sym_val.value), self.width);
+        }
+
+        let opcode = term.sym_val.value as u8;
+        let args: Vec<Bits> = term.args.iter().map(|arg| self.blast(arg)).collect();
+        let width = self.width;
+
+        match (opcode, args.as_slice()) {
+            (ADD, [a, b]) => self.add(a, b),
+            (SUB, [a, b]) => self.sub(a, b),
+            (MUL, [a, b]) => self.mul(a, b),
+            (DIV, [a, b]) => {
+                let (q, _) = self.udivrem(a, b);
+                self.guard_zero(b, &q)
+            }
+            (MOD, [a, b]) => {
+                let (_, r) = self.udivrem(a, b);
+                self.guard_zero(b, &r)
+            }
+            (SDIV, [a, b]) => {
+                let (abs_a, abs_b) = (self.abs(a), self.abs(b));
+                let (q, _) = self.udivrem(&abs_a, &abs_b);
+                let negative = self.xor(a[width - 1], b[width - 1]);
+                let neg_q = self.neg(&q);
+                let q = self.mux_bits(negative, &neg_q, &q);
+                self.guard_zero(b, &q)
+            }
+            (SMOD, [a, b]) => {
+                // The remainder takes the sign of the dividend
+                let (abs_a, abs_b) = (self.abs(a), self.abs(b));
+                let (_, r) = self.udivrem(&abs_a, &abs_b);
+                let neg_r = self.neg(&r);
+                let r = self.mux_bits(a[width - 1], &neg_r, &r);
+                self.guard_zero(b, &r)
+            }
+            (ADDMOD, [a, b, n]) | (MULMOD, [a, b, n]) => {
+                let (a, b, n_wide) = (
+                    self.zero_extend(a, 2 * width),
+                    self.zero_extend(b, 2 * width),
+                    self.zero_extend(n, 2 * width),
+                );
+                let combined = if opcode == ADDMOD {
+                    self.add(&a, &b)
+                } else {
+                    self.mul(&a, &b)
+                };
+                let (_, r) = self.udivrem(&combined, &n_wide);
+                self.guard_zero(n, &r[..width])
+            }
+            (EXP, [base, _]) if is_concrete(&term.args[1]) => {
+                // Square and multiply over the known exponent
+                let mut exponent = term.args[1].sym_val.value;
+                let mut result = self.constant(U256::from(1u64), width);
+                let mut square = base.clone();
+                while exponent > 0 {
+                    if exponent & 1 == 1 {
+                        result = self.mul(&result, &square);
+                    }
+                    exponent >>= 1;
+                    if exponent > 0 {
+                        square = self.mul(&square, &square);
+                    }
+                }
+                result
+            }
+            (SIGNEXTEND, [_, x]) if is_concrete(&term.args[0]) => {
+                let bytes = term.args[0].sym_val.value as usize;
+                if bytes >= width / 8 - 1 {
+                    x.clone()
+                } else {
+                    let sign_bit = 8 * (bytes + 1) - 1;
+                    (0..width)
+                        .map(|i| if i <= sign_bit { x[i] } else { x[sign_bit] })
+                        .collect()
+                }
+            }
+            (LT, [a, b]) => {
+                let lt = self.ult(a, b);
+                self.bool_word(lt)
+            }
+            (GT, [a, b]) => {
+                let gt = self.ult(b, a);
+                self.bool_word(gt)
+            }
+            (SLT, [a, b]) => {
+                let lt = self.slt(a, b);
+                self.bool_word(lt)
+            }
+            (SGT, [a, b]) => {
+                let gt = self.slt(b, a);
+                self.bool_word(gt)
+            }
+            (EQ, [a, b]) => {
+                let eq = self.eq(a, b);
+                self.bool_word(eq)
+            }
+            (ISZERO, [a]) => {
+                let zero = self.is_zero(a);
+                self.bool_word(zero)
+            }
+            (AND, [a, b]) => self.bitwise(a, b, Self::and),
+            (OR, [a, b]) => self.bitwise(a, b, Self::or),
+            (XOR, [a, b]) => self.bitwise(a, b, Self::xor),
+            (NOT, [a]) => self.not_bits(a),
+            (BYTE, [i, x]) => {
+                // Byte i counts from the most significant end of the word
+                let bytes = width / 8;
+                let mut result = self.zero(width);
+                for k in 0..bytes {
+                    let index = self.constant(U256::from(k), width);
+                    let selected = self.eq(i, &index);
+                    let low = (bytes - 1 - k) * 8;
+                    let mut byte = self.zero(width);
+                    byte[..8].copy_from_slice(&x[low..low + 8]);
+                    result = self.mux_bits(selected, &byte, &result);
+                }
+                result
+            }
+            (SHL, [shift, value]) => {
+                let f = self.lit_false();
+                self.shift(value, shift, true, f)
+            }
+            (SHR, [shift, value]) => {
+                let f = self.lit_false();
+                self.shift(value, shift, false, f)
+            }
+            (SAR, [shift, value]) => self.shift(value, shift, false, value[width - 1]),
+            _ => {
+                // Anything we have no semantics for is a free variable
+                let bits: Bits = (0..width).map(|_| self.fresh()).collect();
+                self.vars.insert(term.clone(), bits.clone());
+                bits
+            }
+        }
+    }
+
+    /// Value of a word in the satisfying assignment
+    pub fn value_of(&self, bits: &[Lit]) -> U256 {
+        let mut value = U256::ZERO;
+        for (i, &bit) in bits.iter().enumerate() {
+            if self.sat.value(bit) == Some(true) {
+                value.set_bit(i, true);
+            }
+        }
+        value
+    }
+}
+
+/// Solver backend that bit-blasts the conditions into CNF for the built-in SAT solver. Needs no
+/// external tools, and understands the arithmetic and bitwise conditions DL cannot express.
+#[derive(Debug)]
+pub struct BvSolver {
+    /// Word width in bits
+    pub width: usize,
+    /// Give up with Unknown after this many conflicts per check
+    pub max_conflicts: Option<u64>,
+    assertions: Vec<Branch>,
+    /// Number of assertions when each open scope was pushed
+    scopes: Vec<usize>,
+    /// Model of the last Sat check
+    last_model: Option<Model>,
+}
+
+impl BvSolver {
+    pub fn new(width: usize) -> Self {
+        Self {
+            width,
+            max_conflicts: None,
+            assertions: Vec::new(),
+            scopes: Vec::new(),
+            last_model: None,
+        }
+    }
+}
+
+impl Solver for BvSolver {
+    fn assert_branch(&mut self, branch: &Branch) {
+        self.assertions.push(branch.clone());
+    }
+
+    fn check(&mut self) -> DLResult {
+        // Conditions are re-encoded from scratch on every check
+        let mut blaster = BitBlaster::new(self.width);
+        blaster.sat.max_conflicts = self.max_conflicts;
+        for branch in &self.assertions {
+            let bits = blaster.blast(&branch.condition);
+            if branch.taken {
+                blaster.sat.add_clause(&bits);
+            } else {
+                for bit in bits {
+                    blaster.sat.add_clause(&[!bit]);
+                }
+            }
+        }
+
+        self.last_model = None;
+        match blaster.sat.solve() {
+            SatResult::Sat => {
+                self.last_model = Some(
+                    blaster
+                        .vars
+                        .iter()
+                        .map(|(term, bits)| (term.to_string(), blaster.value_of(bits)))
+                        .collect(),
+                );
+                DLResult::Sat
+            }
+            SatResult::Unsat => DLResult::Unsat,
+            SatResult::Unknown => DLResult::Unknown,
+        }
+    }
+
+    fn model(&mut self) -> Option<Model> {
+        self.last_model.clone()
+    }
+
+    fn push(&mut self) {
+        self.scopes.push(self.assertions.len());
+    }
+
+    fn pop(&mut self) {
+        let len = self.scopes.pop().expect("pop without a matching push");
+        self.assertions.truncate(len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concrete::arithmetic;
+    use crate::sym_stack::{Kind, SymVal};
+
+    fn constant(value: u64) -> Term {
+        Term {
+            sym_val: SymVal {
+                value,
+                kind: Kind::Concrete,
+            },
+            args: Vec::new(),
+        }
+    }
+
+    fn op(opcode: u8, args: Vec<Term>) -> Term {
+        Term {
+            sym_val: SymVal {
+                value: opcode as u64,
+                kind: Kind::Symbolic,
+            },
+            args,
+        }
+    }
+
+    const WIDTH: usize = 8;
+    const SAMPLES: [u64; 12] = [0, 1, 2, 3, 7, 8, 9, 0x40, 0x7f, 0x80, 0x81, 0xff];
+
+    // An 8-bit value as a word, sign extended when the opcode reads it as signed
+    fn widen(value: u64, signed: bool) -> U256 {
+        match signed && value & 0x80 != 0 {
+            true => U256::from(value) | (U256::MAX << WIDTH),
+            false => U256::from(value),
+        }
+    }
+
+    // Blast `opcode` over free inputs pinned to `args`, and read back the result
+    fn blasted(opcode: u8, args: &[u64]) -> u64 {
+        let mut blaster = BitBlaster::new(WIDTH);
+        let inputs: Vec<Term> = (0..args.len())
+            .map(|i| op(CALLDATALOAD, vec![constant(32 * i as u64)]))
+            .collect();
+        for (input, &value) in inputs.iter().zip(args) {
+            for (i, bit) in blaster.blast(input).into_iter().enumerate() {
+                let set = value >> i & 1 == 1;
+                blaster.sat.add_clause(&[if set { bit } else { !bit }]);
+            }
+        }
+        let result = blaster.blast(&op(opcode, inputs));
+        assert_eq!(blaster.sat.solve(), SatResult::Sat);
+        blaster.value_of(&result).to::<u64>()
+    }
+
+    fn agrees(opcode: u8, args: &[u64], signed: &[bool]) {
+        let words: Vec<U256> = args
+            .iter()
+            .zip(signed)
+            .map(|(&value, &signed)| widen(value, signed))
+            .collect();
+        // The low bits of the full word are the bits these opcodes compute at the narrow width
+        let expected = arithmetic(opcode, &words).unwrap() & U256::from(0xff);
+        assert_eq!(
+            blasted(opcode, args),
+            expected.to::<u64>(),
+            "{} {:?}",
+            opcode_name(opcode),
+            args
+        );
+    }
+
+    #[test]
+    fn binary_opcodes_match_the_interpreter() {
+        let unsigned = [ADD, MUL, SUB, DIV, MOD, LT, GT, EQ, AND, OR, XOR, SHL, SHR];
+        for &a in &SAMPLES {
+            for &b in &SAMPLES {
+                for opcode in unsigned {
+                    agrees(opcode, &[a, b], &[false, false]);
+                }
+                for opcode in [SDIV, SMOD, SLT, SGT] {
+                    agrees(opcode, &[a, b], &[true, true]);
+                }
+                // The shift amount is unsigned, the value shifted is signed
+                agrees(SAR, &[a, b], &[false, true]);
+            }
+        }
+    }
+
+    #[test]
+    fn unary_and_ternary_opcodes_match_the_interpreter() {
+        for &a in &SAMPLES {
+            agrees(ISZERO, &[a], &[false]);
+            agrees(NOT, &[a], &[false]);
+            for &b in &SAMPLES {
+                for &n in &[0, 1, 7, 0x80, 0xff] {
+                    agrees(ADDMOD, &[a, b, n], &[false; 3]);
+                    agrees(MULMOD, &[a, b, n], &[false; 3]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn known_exponents_match_the_interpreter() {
+        for &base in &SAMPLES {
+            for exponent in [0, 1, 2, 3, 8, 255] {
+                let mut blaster = BitBlaster::new(WIDTH);
+                let term = op(EXP, vec![constant(base), constant(exponent)]);
+                let bits = blaster.blast(&term);
+                assert_eq!(blaster.sat.solve(), SatResult::Sat);
+                let expected = U256::from(base).wrapping_pow(U256::from(exponent));
+                assert_eq!(
+                    blaster.value_of(&bits),
+                    expected & U256::from(0xff),
+                    "{}^{}",
+                    base,
+                    exponent
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn the_solver_finds_inputs_and_refutes_conditions() {
+        let input = op(CALLDATALOAD, vec![constant(0)]);
+        // x * 3 == 21 has the solution 7 at any width
+        let product = op(MUL, vec![input.clone(), constant(3)]);
+        let condition = op(EQ, vec![product, constant(21)]);
+        let mut solver = BvSolver::new(WIDTH);
+        solver.assert_branch(&Branch {
+            pc: 0,
+            taken: true,
+            condition: condition.clone(),
+        });
+        assert_eq!(solver.check(), DLResult::Sat);
+        let model = solver.model().unwrap();
+        let x = model[&input.to_string()];
+        assert_eq!((x * U256::from(3)) & U256::from(0xff), U256::from(21));
+
+        // x < x never holds
+        solver.push();
+        solver.assert_branch(&Branch {
+            pc: 1,
+            taken: true,
+            condition: op(LT, vec![input.clone(), input]),
+        });
+        assert_eq!(solver.check(), DLResult::Unsat);
+        solver.pop();
+        assert_eq!(solver.check(), DLResult::Sat);
+    }
+}
@@ -0,0 +1,451 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+
+use crate::handlers::sym_handlers;
+use crate::hardfork::Hardfork;
+use crate::opcodes::*;
+
+/// Upper bound on the number of distinct constants tracked for a single stack slot before it is
+/// widened to an unknown value. Internal function returns are the main source of multiple targets.
+const MAX_CONSTS: usize = 16;
+
+/// A straight line sequence of instructions with a single entry and a single exit
+#[derive(Debug, Clone)]
+pub struct BasicBlock {
+    /// Pc of the first instruction in the block
+    pub start: usize,
+    /// Pc of the last instruction in the block
+    pub end: usize,
+    /// (pc, opcode) of every instruction in the block
+    pub instructions: Vec<(usize, u8)>,
+}
+
+impl BasicBlock {
+    /// The opcode that ends the block
+    pub fn last_opcode(&self) -> u8 {
+        self.instructions
+            .last()
+            .map(|(_, opcode)| *opcode)
+            .unwrap_or(STOP)
+    }
+}
+
+/// How control reaches a successor block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EdgeKind {
+    /// Execution falls through into the next block, including the not-taken side of a JUMPI
+    Fallthrough,
+    /// A JUMP, or the taken side of a JUMPI
+    Jump,
+}
+
+/// Static control flow graph over the basic blocks of a runtime bytecode
+#[derive(Debug, Default)]
+pub struct Cfg {
+    /// Basic blocks keyed by their start pc
+    pub blocks: BTreeMap<usize, BasicBlock>,
+    /// Outgoing edges of each block
+    succs: BTreeMap<usize, BTreeSet<(usize, EdgeKind)>>,
+    /// Incoming edges of each block
+    preds: BTreeMap<usize, BTreeSet<(usize, EdgeKind)>>,
+    /// Pcs of JUMP/JUMPI instructions whose target could not be resolved
+    unresolved: BTreeSet<usize>,
+}
+
+/// Decode the bytecode into (pc, opcode) pairs, skipping over PUSH data
+pub fn instructions(code: &[u8]) -> Vec<(usize, u8)> {
+    let mut out = Vec::new();
+    let mut pc = 0;
+    while pc < code.len() {
+        let opcode = code[pc];
+        out.push((pc, opcode));
+        pc += 1 + push_size(opcode);
+    }
+    out
+}
+
+/// Whether `opcode` always ends execution under `fork`, counting opcodes the fork does not define
+/// as INVALID
+fn halts(opcode: u8, fork: Hardfork) -> bool {
+    is_terminating(opcode)
+        || Hardfork::introduced(opcode).is_none_or(|introduced| introduced > fork)
+}
+
+/// Bitmap of valid jump destinations: JUMPDEST opcodes that are not part of PUSH data
+pub fn valid_jumpdests(code: &[u8]) -> Vec<bool> {
+    let mut jumpdests = vec![false; code.len()];
+    for (pc, opcode) in instructions(code) {
+        if opcode == JUMPDEST {
+            jumpdests[pc] = true;
+        }
+    }
+    jumpdests
+}
+
+/// Read the immediate of the PUSH at `pc`, if it fits in 64 bits
+pub fn push_value(code: &[u8], pc: usize) -> Option<u64> {
+    let size = push_size(code[pc]);
+    let mut word: u64 = 0;
+    for i in 0..size {
+        // Immediates running past the end of the code are zero padded
+        let byte = code.get(pc + 1 + i).copied().unwrap_or(0);
+        if word >> 56 != 0 {
+            return None;
+        }
+        word = (word << 8) | byte as u64;
+    }
+    Some(word)
+}
+
+impl Cfg {
+    /// Split the bytecode into basic blocks and resolve the jump edges between them, with the
+    /// opcodes `fork` defines
+    pub fn new(code: &[u8], fork: Hardfork) -> Self {
+        let mut cfg = Cfg::default();
+        let instrs = instructions(code);
+
+        // A new block starts at the entry, at every JUMPDEST and after every block exit
+        let mut current: Vec<(usize, u8)> = Vec::new();
+        for &(pc, opcode) in &instrs {
+            if opcode == JUMPDEST && !current.is_empty() {
+                cfg.add_block(std::mem::take(&mut current));
+            }
+            current.push((pc, opcode));
+            if opcode == JUMP || opcode == JUMPI || halts(opcode, fork) {
+                cfg.add_block(std::mem::take(&mut current));
+            }
+        }
+        if !current.is_empty() {
+            cfg.add_block(current);
+        }
+
+        cfg.resolve_edges(code, fork);
+        cfg
+    }
+
+    fn add_block(&mut self, instructions: Vec<(usize, u8)>) {
+        let start = instructions[0].0;
+        let end = instructions[instructions.len() - 1].0;
+        self.succs.insert(start, BTreeSet::new());
+        self.preds.insert(start, BTreeSet::new());
+        self.blocks.insert(
+            start,
+            BasicBlock {
+                start,
+                end,
+                instructions,
+            },
+        );
+    }
+
+    fn add_edge(&mut self, from: usize, to: usize, kind: EdgeKind) -> bool {
+        self.preds.entry(to).or_default().insert((from, kind));
+        self.succs.entry(from).or_default().insert((to, kind))
+    }
+
+    /// Whether `pc` is a JUMPDEST that starts a block, and so a legal jump target
+    pub fn is_jumpdest(&self, pc: usize) -> bool {
+        self.blocks
+            .get(&pc)
+            .is_some_and(|block| block.instructions[0].1 == JUMPDEST)
+    }
+
+    /// Stack-height-aware constant propagation over the blocks. Each block entry keeps an abstract
+    /// stack, aligned from the top, whose slots hold a small set of possible constants. Jump targets
+    /// are read off the abstract stack at the end of each block and new edges are followed until
+    /// nothing changes.
+    fn resolve_edges(&mut self, code: &[u8], fork: Hardfork) {
+        let handlers = sym_handlers(fork);
+        let mut entry_states: BTreeMap<usize, AbsStack> = BTreeMap::new();
+        let mut worklist: VecDeque<usize> = VecDeque::new();
+
+        if let Some(&entry) = self.blocks.keys().next() {
+            entry_states.insert(entry, AbsStack::default());
+            worklist.push_back(entry);
+        }
+
+        while let Some(start) = worklist.pop_front() {
+            let block = self.blocks[&start].clone();
+            let mut stack = entry_states[&start].clone();
+            let mut targets: Vec<(usize, EdgeKind)> = Vec::new();
+
+            for &(pc, opcode) in &block.instructions {
+                match opcode {
+                    _ if is_push(opcode) => stack.push(match push_value(code, pc) {
+                        Some(value) => AbsVal::constant(value),
+                        None => AbsVal::Top,
+                    }),
+                    DUP1..=DUP16 => stack.dup((opcode - DUP1) as usize + 1),
+                    SWAP1..=SWAP16 => stack.swap((opcode - SWAP1) as usize + 1),
+                    PC => stack.push(AbsVal::constant(pc as u64)),
+                    JUMP | JUMPI => {
+                        match stack.pop() {
+                            AbsVal::Top => {
+                                self.unresolved.insert(pc);
+                            }
+                            AbsVal::Consts(values) => {
+                                for value in values {
+                                    if self.is_jumpdest(value as usize) {
+                                        targets.push((value as usize, EdgeKind::Jump));
+                                    }
+                                }
+                            }
+                        }
+                        if opcode == JUMPI {
+                            stack.pop();
+                        }
+                    }
+                    _ => {
+                        let handler = &handlers[opcode as usize];
+                        for _ in 0..handler.in_args {
+                            stack.pop();
+                        }
+                        for _ in 0..handler.out_args {
+                            stack.push(AbsVal::Top);
+                        }
+                    }
+                }
+            }
+
+            // Everything but an unconditional jump or a halt falls through to the next block
+            let last = block.last_opcode();
+            if last != JUMP && !halts(last, fork) {
+                if let Some((&next, _)) = self.blocks.range(block.end + 1..).next() {
+                    targets.push((next, EdgeKind::Fallthrough));
+                }
+            }
+
+            for (target, kind) in targets {
+                let changed = match entry_states.get_mut(&target) {
+                    Some(existing) => existing.join(&stack),
+                    None => {
+                        entry_states.insert(target, stack.clone());
+                        true
+                    }
+                };
+                let new_edge = self.add_edge(start, target, kind);
+                if (changed || new_edge) && !worklist.contains(&target) {
+                    worklist.push_back(target);
+                }
+            }
+        }
+    }
+
+    /// The block containing `pc`
+    pub fn block_at(&self, pc: usize) -> Option<&BasicBlock> {
+        self.blocks
+            .range(..=pc)
+            .next_back()
+            .map(|(_, block)| block)
+            .filter(|block| pc <= block.end)
+    }
+
+    /// Start pcs of the blocks control can flow to from the block starting at `start`
+    pub fn successors(&self, start: usize) -> Vec<usize> {
+        self.successor_edges(start)
+            .into_iter()
+            .map(|(to, _)| to)
+            .collect()
+    }
+
+    /// Successor edges of the block starting at `start`, with how each one is taken
+    pub fn successor_edges(&self, start: usize) -> Vec<(usize, EdgeKind)> {
+        self.succs
+            .get(&start)
+            .map(|edges| edges.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Start pcs of the blocks control can flow from into the block starting at `start`
+    pub fn predecessors(&self, start: usize) -> Vec<usize> {
+        self.preds
+            .get(&start)
+            .map(|edges| edges.iter().map(|(from, _)| *from).collect())
+            .unwrap_or_default()
+    }
+
+    /// Start pcs of every block reachable from the entry, ignoring path feasibility
+    pub fn reachable(&self) -> BTreeSet<usize> {
+        match self.blocks.keys().next() {
+            Some(&entry) => self.reachable_from(entry),
+            None => BTreeSet::new(),
+        }
+    }
+
+    /// Start pcs of every block reachable from the block starting at `start`
+    pub fn reachable_from(&self, start: usize) -> BTreeSet<usize> {
+        let mut seen = BTreeSet::new();
+        let mut stack = vec![start];
+        while let Some(block) = stack.pop() {
+            if seen.insert(block) {
+                stack.extend(self.successors(block));
+            }
+        }
+        seen
+    }
+
+    /// Whether the block containing `pc` can be reached from the entry
+    pub fn is_reachable(&self, pc: usize) -> bool {
+        self.block_at(pc)
+            .is_some_and(|block| self.reachable().contains(&block.start))
+    }
+
+    /// Pcs of the jumps whose target depends on a value we could not track
+    pub fn unresolved_jumps(&self) -> &BTreeSet<usize> {
+        &self.unresolved
+    }
+}
+
+impl fmt::Display for Cfg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for block in self.blocks.values() {
+            let succs: Vec<String> = self
+                .successor_edges(block.start)
+                .iter()
+                .map(|(to, kind)| format!("{:#x} ({:?})", to, kind))
+                .collect();
+            writeln!(
+                f,
+                "block {:#x}..={:#x} -> [{}]",
+                block.start,
+                block.end,
+                succs.join(", ")
+            )?;
+        }
+        for pc in &self.unresolved {
+            writeln!(f, "unresolved jump at {:#x}", pc)?;
+        }
+        Ok(())
+    }
+}
+
+/// Abstract value of a stack slot
+#[derive(Debug, Clone, PartialEq)]
+enum AbsVal {
+    /// Could be anything
+    Top,
+    /// One of a known set of constants
+    Consts(BTreeSet<u64>),
+}
+
+impl AbsVal {
+    fn constant(value: u64) -> Self {
+        AbsVal::Consts(BTreeSet::from([value]))
+    }
+
+    fn join(&self, other: &AbsVal) -> AbsVal {
+        match (self, other) {
+            (AbsVal::Consts(a), AbsVal::Consts(b)) => {
+                let union: BTreeSet<u64> = a.union(b).copied().collect();
+                if union.len() > MAX_CONSTS {
+                    AbsVal::Top
+                } else {
+                    AbsVal::Consts(union)
+                }
+            }
+            _ => AbsVal::Top,
+        }
+    }
+}
+
+/// Abstract stack, top at the end. Slots below the tracked values are unknown.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct AbsStack {
+    values: Vec<AbsVal>,
+}
+
+impl AbsStack {
+    fn push(&mut self, value: AbsVal) {
+        self.values.push(value);
+    }
+
+    fn pop(&mut self) -> AbsVal {
+        self.values.pop().unwrap_or(AbsVal::Top)
+    }
+
+    /// Make sure at least `depth` slots are tracked, padding the bottom with unknowns
+    fn ensure(&mut self, depth: usize) {
+        if self.values.len() < depth {
+            let missing = depth - self.values.len();
+            self.values
+                .splice(0..0, std::iter::repeat_n(AbsVal::Top, missing));
+        }
+    }
+
+    fn dup(&mut self, n: usize) {
+        self.ensure(n);
+        let value = self.values[self.values.len() - n].clone();
+        self.values.push(value);
+    }
+
+    fn swap(&mut self, n: usize) {
+        self.ensure(n + 1);
+        let top = self.values.len() - 1;
+        self.values.swap(top, top - n);
+    }
+
+    /// Merge another stack into this one, aligning both from the top. Returns whether this stack
+    /// changed.
+    fn join(&mut self, other: &AbsStack) -> bool {
+        let height = self.values.len().min(other.values.len());
+        let ours = &self.values[self.values.len() - height..];
+        let theirs = &other.values[other.values.len() - height..];
+        let joined: Vec<AbsVal> = ours.iter().zip(theirs).map(|(a, b)| a.join(b)).collect();
+        if joined == self.values {
+            return false;
+        }
+        self.values = joined;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::EvmContext;
+
+    #[test]
+    fn opcodes_of_later_forks_end_the_block() {
+        // push0; push1 1; stop
+        let code = hex::decode("5f600100").unwrap();
+        let cfg = Cfg::new(&code, Hardfork::Prague);
+        assert_eq!(cfg.blocks.len(), 1);
+        assert!(cfg.is_reachable(1));
+
+        let cfg = Cfg::new(&code, Hardfork::Frontier);
+        assert_eq!(cfg.blocks.keys().copied().collect::<Vec<_>>(), vec![0, 1]);
+        assert!(!cfg.is_reachable(1));
+    }
+
+    #[test]
+    fn jumps_resolve_through_the_stack() {
+        // push1 6; dup1; pop; jump; invalid; jumpdest; push1 10; jump; jumpdest; stop
+        let code = hex::decode("6006805056fe5b600a565b00").unwrap();
+        let cfg = Cfg::new(&code, Hardfork::default());
+        assert!(cfg.unresolved_jumps().is_empty());
+        assert_eq!(cfg.successors(0), vec![6]);
+        assert_eq!(cfg.successors(6), vec![10]);
+        assert!(cfg.is_reachable(10));
+        assert!(!cfg.is_reachable(5));
+    }
+
+    #[test]
+    fn computed_targets_stay_unresolved() {
+        // calldataload(0); jump
+        let code = hex::decode("5f3556").unwrap();
+        let cfg = Cfg::new(&code, Hardfork::default());
+        assert_eq!(cfg.unresolved_jumps(), &BTreeSet::from([2]));
+    }
+
+    #[test]
+    fn jumpdests_inside_push_data_are_invalid() {
+        // push1 0x5b; jumpdest; stop
+        let code = hex::decode("605b5b00").unwrap();
+        assert_eq!(valid_jumpdests(&code), vec![false, false, true, false]);
+
+        let context = EvmContext::new(code);
+        assert!(context.is_valid_jump(2));
+        assert!(!context.is_valid_jump(1));
+        assert!(!context.is_valid_jump(u64::MAX));
+    }
+}
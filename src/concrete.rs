@@ -0,0 +1,747 @@
+use ruint::aliases::U256;
+use std::collections::BTreeMap;
+use std::fmt;
+use tiny_keccak::{Hasher, Keccak};
+
+use crate::cfg::valid_jumpdests;
+use crate::handlers::{HaltReason, HandlerType, OpcodeHandler};
+use crate::opcodes::*;
+use crate::sym_stack::{is_concrete, Term};
+
+/// Most items the stack may hold
+pub(crate) const STACK_LIMIT: usize = 1024;
+
+/// Largest memory we are willing to allocate, anything past it is treated as running out of gas
+const MEMORY_LIMIT: usize = 1 << 24;
+
+/// Block and transaction environment seen by the code
+#[derive(Debug, Clone)]
+pub struct Env {
+    pub address: U256,
+    pub caller: U256,
+    pub origin: U256,
+    pub callvalue: U256,
+    pub gasprice: U256,
+    pub coinbase: U256,
+    pub timestamp: U256,
+    pub number: U256,
+    pub prevrandao: U256,
+    pub gaslimit: U256,
+    pub chainid: U256,
+    pub basefee: U256,
+    pub blobbasefee: U256,
+    /// Balance of the executing account
+    pub balance: U256,
+    /// Value GAS reports, there is no gas accounting
+    pub gas: U256,
+    /// Whether external calls succeed. There are no other accounts to run, so calls return no data.
+    pub call_success: bool,
+}
+
+impl Default for Env {
+    fn default() -> Self {
+        Self {
+            address: U256::from(0xc0ffee),
+            caller: U256::from(0xca11e7),
+            origin: U256::from(0xca11e7),
+            callvalue: U256::ZERO,
+            gasprice: U256::from(1),
+            coinbase: U256::ZERO,
+            timestamp: U256::from(1),
+            number: U256::from(1),
+            prevrandao: U256::ZERO,
+            gaslimit: U256::from(30_000_000),
+            chainid: U256::from(1),
+            basefee: U256::from(1),
+            blobbasefee: U256::from(1),
+            balance: U256::ZERO,
+            gas: U256::from(30_000_000),
+            call_success: true,
+        }
+    }
+}
+
+/// Everything a concrete execution starts from
+#[derive(Debug, Clone, Default)]
+pub struct ConcreteInput {
+    pub calldata: Vec<u8>,
+    pub env: Env,
+    /// Persistent storage before the transaction, unset slots are zero
+    pub storage: BTreeMap<U256, U256>,
+}
+
+/// One executed instruction
+#[derive(Debug, Clone)]
+pub struct TraceStep {
+    pub pc: usize,
+    pub opcode: u8,
+    /// Stack before the instruction, top last
+    pub stack: Vec<U256>,
+}
+
+/// A LOG record
+#[derive(Debug, Clone)]
+pub struct Log {
+    pub topics: Vec<U256>,
+    pub data: Vec<u8>,
+}
+
+/// Outcome of a concrete execution
+#[derive(Debug, Clone)]
+pub struct ExecutionResult {
+    pub trace: Vec<TraceStep>,
+    /// Output of RETURN or REVERT
+    pub return_data: Vec<u8>,
+    /// SSTOREs in execution order, including ones a revert later undoes
+    pub storage_writes: Vec<(U256, U256)>,
+    /// Persistent storage after the transaction, unchanged when it reverted
+    pub storage: BTreeMap<U256, U256>,
+    pub logs: Vec<Log>,
+    /// Stack when execution ended, top last
+    pub stack: Vec<U256>,
+    /// Memory when execution ended
+    pub memory: Vec<u8>,
+    /// Opcode that ended execution
+    pub halt_opcode: u8,
+    /// Pc of the instruction that ended execution
+    pub halt_pc: usize,
+    /// Set when execution ended with an exceptional halt
+    pub halt: Option<HaltReason>,
+}
+
+impl ExecutionResult {
+    /// Whether the transaction completed without reverting or halting exceptionally
+    pub fn success(&self) -> bool {
+        self.halt.is_none() && ![REVERT, INVALID].contains(&self.halt_opcode)
+    }
+}
+
+/// Concrete EVM interpreter driven by the same handler table as the symbolic engine. The table
+/// decides the stack effect of every opcode and which ones are defined in the hardfork, the
+/// interpreter supplies the values.
+pub struct ConcreteEvm {
+    pub handlers: [OpcodeHandler; 256],
+    /// Give up with OutOfGas after this many instructions
+    pub max_steps: usize,
+}
+
+/// State of one running execution
+struct Machine<'a> {
+    code: &'a [u8],
+    input: &'a ConcreteInput,
+    stack: Vec<U256>,
+    memory: Vec<u8>,
+    /// Output of the last external call
+    return_buffer: Vec<u8>,
+    storage: BTreeMap<U256, U256>,
+    transient_storage: BTreeMap<U256, U256>,
+    storage_writes: Vec<(U256, U256)>,
+    logs: Vec<Log>,
+    pc: usize,
+}
+
+impl ConcreteEvm {
+    pub fn new(handlers: [OpcodeHandler; 256]) -> Self {
+        Self {
+            handlers,
+            max_steps: 1_000_000,
+        }
+    }
+
+    /// Run `code` as a single transaction on `input`
+    pub fn execute(&self, code: &[u8], input: &ConcreteInput) -> ExecutionResult {
+        let jumpdests = valid_jumpdests(code);
+        let mut machine = Machine {
+            code,
+            input,
+            stack: Vec::new(),
+            memory: Vec::new(),
+            return_buffer: Vec::new(),
+            storage: input.storage.clone(),
+            transient_storage: BTreeMap::new(),
+            storage_writes: Vec::new(),
+            logs: Vec::new(),
+            pc: 0,
+        };
+        let mut trace = Vec::new();
+        let mut return_data = Vec::new();
+
+        let (halt_opcode, halt_pc, halt) = loop {
+            let pc = machine.pc;
+            // Running off the end of the code is an implicit STOP
+            if pc >= code.len() {
+                break (STOP, pc, None);
+            }
+            let opcode = code[pc];
+            if trace.len() >= self.max_steps {
+                break (opcode, pc, Some(HaltReason::OutOfGas));
+            }
+            trace.push(TraceStep {
+                pc,
+                opcode,
+                stack: machine.stack.clone(),
+            });
+
+            let handler = &self.handlers[opcode as usize];
+            let in_args = handler.in_args as usize;
+            let out_args = handler.out_args as usize;
+            if handler.handler_type == HandlerType::Invalid {
+                break (opcode, pc, Some(HaltReason::InvalidOpcode(opcode)));
+            }
+            if machine.stack.len() < in_args {
+                break (opcode, pc, Some(HaltReason::StackUnderflow));
+            }
+            if machine.stack.len() - in_args + out_args > STACK_LIMIT {
+                break (opcode, pc, Some(HaltReason::StackOverflow));
+            }
+
+            match handler.handler_type {
+                HandlerType::Push => {
+                    // Immediates running past the end of the code read as zeros
+                    let mut bytes = [0u8; 32];
+                    let reads = handler.bytecode_reads as usize;
+                    for i in 0..reads {
+                        bytes[32 - reads + i] = code.get(pc + 1 + i).copied().unwrap_or(0);
+                    }
+                    machine.stack.push(U256::from_be_bytes(bytes));
+                    machine.pc += 1 + reads;
+                }
+                HandlerType::Dup => {
+                    let value = machine.stack[machine.stack.len() - in_args];
+                    machine.stack.push(value);
+                    machine.pc += 1;
+                }
+                HandlerType::Swap => {
+                    let top = machine.stack.len() - 1;
+                    machine.stack.swap(top, top + 1 - in_args);
+                    machine.pc += 1;
+                }
+                HandlerType::Jump | HandlerType::JumpI => {
+                    let target = machine.pop();
+                    let condition = match handler.handler_type {
+                        HandlerType::JumpI => machine.pop(),
+                        _ => U256::from(1),
+                    };
+                    if condition.is_zero() {
+                        machine.pc += 1;
+                        continue;
+                    }
+                    let destination = usize::try_from(target).unwrap_or(usize::MAX);
+                    if !jumpdests.get(destination).copied().unwrap_or(false) {
+                        break (opcode, pc, Some(HaltReason::InvalidJump(destination)));
+                    }
+                    machine.pc = destination;
+                }
+                HandlerType::Terminating => {
+                    let args = machine.pop_n(in_args);
+                    if opcode == RETURN || opcode == REVERT {
+                        match machine.read_memory(args[0], args[1]) {
+                            Ok(data) => return_data = data,
+                            Err(halt) => break (opcode, pc, Some(halt)),
+                        }
+                    }
+                    break (opcode, pc, None);
+                }
+                HandlerType::SLoad | HandlerType::TLoad => {
+                    let key = machine.pop();
+                    let slots = match handler.handler_type {
+                        HandlerType::SLoad => &machine.storage,
+                        _ => &machine.transient_storage,
+                    };
+                    let value = slots.get(&key).copied().unwrap_or_default();
+                    machine.stack.push(value);
+                    machine.pc += 1;
+                }
+                HandlerType::SStore | HandlerType::TStore => {
+                    let key = machine.pop();
+                    let value = machine.pop();
+                    if handler.handler_type == HandlerType::SStore {
+                        machine.storage_writes.push((key, value));
+                        machine.storage.insert(key, value);
+                    } else {
+                        machine.transient_storage.insert(key, value);
+                    }
+                    machine.pc += 1;
+                }
+                HandlerType::Unimplemented
+                | HandlerType::Sha3
+                | HandlerType::MLoad
+                | HandlerType::MStore
+                | HandlerType::MemoryWrite => {
+                    let args = machine.pop_n(in_args);
+                    match machine.evaluate(opcode, &args) {
+                        Ok(value) => {
+                            if out_args > 0 {
+                                machine.stack.push(value);
+                            }
+                        }
+                        Err(halt) => break (opcode, pc, Some(halt)),
+                    }
+                    machine.pc += 1;
+                }
+                HandlerType::Invalid => unreachable!(),
+            }
+        };
+
+        let result = ExecutionResult {
+            trace,
+            return_data,
+            storage_writes: machine.storage_writes,
+            storage: machine.storage,
+            logs: machine.logs,
+            stack: machine.stack,
+            memory: machine.memory,
+            halt_opcode,
+            halt_pc,
+            halt,
+        };
+        match result.success() {
+            true => result,
+            false => ExecutionResult {
+                storage: input.storage.clone(),
+                logs: Vec::new(),
+                ..result
+            },
+        }
+    }
+}
+
+impl Machine<'_> {
+    fn pop(&mut self) -> U256 {
+        self.stack
+            .pop()
+            .expect("stack depth checked against the handler")
+    }
+
+    /// Pop `n` items, the top of the stack first
+    fn pop_n(&mut self, n: usize) -> Vec<U256> {
+        (0..n).map(|_| self.pop()).collect()
+    }
+
+    /// Grow memory to cover `size` bytes at `offset` and return the range as indices
+    fn expand(&mut self, offset: U256, size: U256) -> Result<(usize, usize), HaltReason> {
+        if size.is_zero() {
+            return Ok((0, 0));
+        }
+        let offset = usize::try_from(offset).map_err(|_| HaltReason::OutOfGas)?;
+        let size = usize::try_from(size).map_err(|_| HaltReason::OutOfGas)?;
+        let end = offset
+            .checked_add(size)
+            .filter(|&end| end <= MEMORY_LIMIT)
+            .ok_or(HaltReason::OutOfGas)?;
+        if end > self.memory.len() {
+            // Memory grows in whole words
+            self.memory.resize(end.div_ceil(32) * 32, 0);
+        }
+        Ok((offset, size))
+    }
+
+    fn read_memory(&mut self, offset: U256, size: U256) -> Result<Vec<u8>, HaltReason> {
+        let (offset, size) = self.expand(offset, size)?;
+        Ok(self.memory[offset..offset + size].to_vec())
+    }
+
+    /// Copy `size` bytes of `source` starting at `from` to memory at `offset`, padding with zeros
+    fn copy_to_memory(
+        &mut self,
+        source: &[u8],
+        offset: U256,
+        from: U256,
+        size: U256,
+    ) -> Result<(), HaltReason> {
+        let (offset, size) = self.expand(offset, size)?;
+        let from = usize::try_from(from).unwrap_or(usize::MAX);
+        for i in 0..size {
+            self.memory[offset + i] = from
+                .checked_add(i)
+                .and_then(|index| source.get(index))
+                .copied()
+                .unwrap_or(0);
+        }
+        Ok(())
+    }
+
+    /// Value of an opcode without special control flow, from its arguments with the top of the
+    /// stack first. Opcodes pushing nothing return zero.
+    fn evaluate(&mut self, opcode: u8, args: &[U256]) -> Result<U256, HaltReason> {
+        if let Some(value) = arithmetic(opcode, args) {
+            return Ok(value);
+        }
+
+        let input = self.input;
+        let env = &input.env;
+        let value = match opcode {
+            SHA3 => keccak(&self.read_memory(args[0], args[1])?),
+
+            ADDRESS => env.address,
+            BALANCE => match args[0] == env.address {
+                true => env.balance,
+                false => U256::ZERO,
+            },
+            ORIGIN => env.origin,
+            CALLER => env.caller,
+            CALLVALUE => env.callvalue,
+            CALLDATALOAD => {
+                let from = usize::try_from(args[0]).unwrap_or(usize::MAX);
+                let mut word = [0u8; 32];
+                for (i, byte) in word.iter_mut().enumerate() {
+                    *byte = from
+                        .checked_add(i)
+                        .and_then(|index| input.calldata.get(index))
+                        .copied()
+                        .unwrap_or(0);
+                }
+                U256::from_be_bytes(word)
+            }
+            CALLDATASIZE => U256::from(self.input.calldata.len()),
+            CALLDATACOPY => {
+                self.copy_to_memory(&input.calldata, args[0], args[1], args[2])?;
+                U256::ZERO
+            }
+            CODESIZE => U256::from(self.code.len()),
+            CODECOPY => {
+                self.copy_to_memory(self.code, args[0], args[1], args[2])?;
+                U256::ZERO
+            }
+            GASPRICE => env.gasprice,
+            EXTCODESIZE => match args[0] == env.address {
+                true => U256::from(self.code.len()),
+                false => U256::ZERO,
+            },
+            EXTCODECOPY => {
+                let code: &[u8] = match args[0] == env.address {
+                    true => self.code,
+                    false => &[],
+                };
+                self.copy_to_memory(code, args[1], args[2], args[3])?;
+                U256::ZERO
+            }
+            RETURNDATASIZE => U256::from(self.return_buffer.len()),
+            RETURNDATACOPY => {
+                let end = args[1].checked_add(args[2]);
+                if end.is_none_or(|end| end > U256::from(self.return_buffer.len())) {
+                    return Err(HaltReason::ReturnDataOutOfBounds);
+                }
+                let buffer = std::mem::take(&mut self.return_buffer);
+                let copied = self.copy_to_memory(&buffer, args[0], args[1], args[2]);
+                self.return_buffer = buffer;
+                copied?;
+                U256::ZERO
+            }
+            EXTCODEHASH => match args[0] == env.address {
+                true => keccak(self.code),
+                false => U256::ZERO,
+            },
+            BLOCKHASH => U256::ZERO,
+            COINBASE => env.coinbase,
+            TIMESTAMP => env.timestamp,
+            NUMBER => env.number,
+            DIFFICULTY => env.prevrandao,
+            GASLIMIT => env.gaslimit,
+            CHAINID => env.chainid,
+            SELFBALANCE => env.balance,
+            BASEFEE => env.basefee,
+            BLOBHASH => U256::ZERO,
+            BLOBBASEFEE => env.blobbasefee,
+
+            POP | JUMPDEST => U256::ZERO,
+            MLOAD => U256::from_be_slice(&self.read_memory(args[0], U256::from(32))?),
+            MSTORE => {
+                let (offset, _) = self.expand(args[0], U256::from(32))?;
+                self.memory[offset..offset + 32].copy_from_slice(&args[1].to_be_bytes::<32>());
+                U256::ZERO
+            }
+            MSTORE8 => {
+                let (offset, _) = self.expand(args[0], U256::from(1))?;
+                self.memory[offset] = args[1].byte(0);
+                U256::ZERO
+            }
+            PC => U256::from(self.pc),
+            MSIZE => U256::from(self.memory.len()),
+            GAS => env.gas,
+            MCOPY => {
+                let source = self.read_memory(args[1], args[2])?;
+                self.copy_to_memory(&source, args[0], U256::ZERO, args[2])?;
+                U256::ZERO
+            }
+
+            LOG0..=LOG4 => {
+                let data = self.read_memory(args[0], args[1])?;
+                self.logs.push(Log {
+                    topics: args[2..].to_vec(),
+                    data,
+                });
+                U256::ZERO
+            }
+
+            // Nothing is deployed, creations fail
+            CREATE | CREATE2 => {
+                self.return_buffer.clear();
+                U256::ZERO
+            }
+            CALL | CALLCODE | DELEGATECALL | STATICCALL => {
+                // Input and output areas still expand memory
+                let (input, output) = match opcode {
+                    CALL | CALLCODE => (3, 5),
+                    _ => (2, 4),
+                };
+                self.expand(args[input], args[input + 1])?;
+                self.expand(args[output], args[output + 1])?;
+                self.return_buffer.clear();
+                U256::from(env.call_success)
+            }
+
+            _ => panic!(
+                "Unhandled opcode {} in concrete execution",
+                opcode_name(opcode)
+            ),
+        };
+        Ok(value)
+    }
+}
+
+/// Value of an opcode that only depends on its arguments, given with the top of the stack first
+pub fn arithmetic(opcode: u8, args: &[U256]) -> Option<U256> {
+    let value = match opcode {
+        ADD => args[0].wrapping_add(args[1]),
+        MUL => args[0].wrapping_mul(args[1]),
+        SUB => args[0].wrapping_sub(args[1]),
+        DIV => args[0].checked_div(args[1]).unwrap_or_default(),
+        SDIV => sdiv(args[0], args[1]),
+        MOD => args[0].checked_rem(args[1]).unwrap_or_default(),
+        SMOD => smod(args[0], args[1]),
+        ADDMOD => args[0].add_mod(args[1], args[2]),
+        MULMOD => args[0].mul_mod(args[1], args[2]),
+        EXP => args[0].wrapping_pow(args[1]),
+        SIGNEXTEND => signextend(args[0], args[1]),
+        LT => U256::from(args[0] < args[1]),
+        GT => U256::from(args[0] > args[1]),
+        SLT => U256::from(signed_lt(args[0], args[1])),
+        SGT => U256::from(signed_lt(args[1], args[0])),
+        EQ => U256::from(args[0] == args[1]),
+        ISZERO => U256::from(args[0].is_zero()),
+        AND => args[0] & args[1],
+        OR => args[0] | args[1],
+        XOR => args[0] ^ args[1],
+        NOT => !args[0],
+        BYTE => match usize::try_from(args[0]) {
+            Ok(index) if index < 32 => U256::from(args[1].byte(31 - index)),
+            _ => U256::ZERO,
+        },
+        SHL => match usize::try_from(args[0]) {
+            Ok(shift) if shift < 256 => args[1] << shift,
+            _ => U256::ZERO,
+        },
+        SHR => match usize::try_from(args[0]) {
+            Ok(shift) if shift < 256 => args[1] >> shift,
+            _ => U256::ZERO,
+        },
+        SAR => args[1].arithmetic_shr(usize::try_from(args[0]).unwrap_or(256).min(256)),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Value of a term built from constants by opcodes that only depend on their arguments
+pub fn evaluate_term(term: &Term) -> Option<U256> {
+    if is_concrete(term) {
+        return Some(U256::from(term.sym_val.value));
+    }
+    let args = term
+        .args
+        .iter()
+        .map(evaluate_term)
+        .collect::<Option<Vec<U256>>>()?;
+    arithmetic(term.sym_val.value as u8, &args)
+}
+
+fn keccak(data: &[u8]) -> U256 {
+    let mut hasher = Keccak::v256();
+    let mut hash = [0u8; 32];
+    hasher.update(data);
+    hasher.finalize(&mut hash);
+    U256::from_be_bytes(hash)
+}
+
+fn is_negative(value: U256) -> bool {
+    value.bit(255)
+}
+
+// Absolute value of a two's complement word
+fn abs(value: U256) -> U256 {
+    match is_negative(value) {
+        true => value.wrapping_neg(),
+        false => value,
+    }
+}
+
+fn signed_lt(a: U256, b: U256) -> bool {
+    match (is_negative(a), is_negative(b)) {
+        (true, false) => true,
+        (false, true) => false,
+        _ => a < b,
+    }
+}
+
+fn sdiv(a: U256, b: U256) -> U256 {
+    if b.is_zero() {
+        return U256::ZERO;
+    }
+    // The minimum value divided by -1 wraps back to itself, as does the negation below
+    let quotient = abs(a) / abs(b);
+    match is_negative(a) != is_negative(b) {
+        true => quotient.wrapping_neg(),
+        false => quotient,
+    }
+}
+
+fn smod(a: U256, b: U256) -> U256 {
+    if b.is_zero() {
+        return U256::ZERO;
+    }
+    // The result takes the sign of the dividend
+    let remainder = abs(a) % abs(b);
+    match is_negative(a) {
+        true => remainder.wrapping_neg(),
+        false => remainder,
+    }
+}
+
+fn signextend(bytes: U256, value: U256) -> U256 {
+    let bytes = match usize::try_from(bytes) {
+        Ok(bytes) if bytes < 31 => bytes,
+        _ => return value,
+    };
+    let sign_bit = bytes * 8 + 7;
+    let mask = (U256::from(1) << (sign_bit + 1)).wrapping_sub(U256::from(1));
+    match value.bit(sign_bit) {
+        true => value | !mask,
+        false => value & mask,
+    }
+}
+
+impl fmt::Display for ExecutionResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.trace {
+            let stack: Vec<String> = step.stack.iter().map(|v| format!("{:#x}", v)).collect();
+            writeln!(
+                f,
+                "{:#06x}  {:<14} [{}]",
+                step.pc,
+                opcode_name(step.opcode),
+                stack.join(", ")
+            )?;
+        }
+        for (key, value) in &self.storage_writes {
+            writeln!(f, "SSTORE: {:#x} = {:#x}", key, value)?;
+        }
+        for log in &self.logs {
+            let topics: Vec<String> = log.topics.iter().map(|t| format!("{:#x}", t)).collect();
+            writeln!(
+                f,
+                "LOG: [{}] 0x{}",
+                topics.join(", "),
+                hex::encode(&log.data)
+            )?;
+        }
+        writeln!(f, "RETURN DATA: 0x{}", hex::encode(&self.return_data))?;
+        match &self.halt {
+            Some(halt) => writeln!(f, "HALT: {:?} at {:#x}", halt, self.halt_pc),
+            None => writeln!(
+                f,
+                "HALT: {} at {:#x}",
+                opcode_name(self.halt_opcode),
+                self.halt_pc
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::sym_handlers;
+    use crate::hardfork::Hardfork;
+
+    fn execute(code: &str, input: &ConcreteInput) -> ExecutionResult {
+        let evm = ConcreteEvm::new(sym_handlers(Hardfork::default()));
+        evm.execute(&hex::decode(code).unwrap(), input)
+    }
+
+    #[test]
+    fn reverts_undo_storage_and_logs_but_keep_the_output() {
+        // sstore(0, 1); log0(0, 0); mstore(0, 42); revert(0, 32)
+        let result = execute(
+            "600160005560006000a0602a60005260206000fd",
+            &Default::default(),
+        );
+        assert!(!result.success());
+        assert_eq!(result.halt_opcode, REVERT);
+        assert_eq!(result.storage_writes, vec![(U256::ZERO, U256::from(1))]);
+        assert!(result.storage.is_empty());
+        assert!(result.logs.is_empty());
+        assert_eq!(
+            result.return_data,
+            U256::from(42).to_be_bytes::<32>().to_vec()
+        );
+    }
+
+    #[test]
+    fn storage_starts_from_the_input() {
+        // sstore(1, add(sload(0), 1))
+        let input = ConcreteInput {
+            storage: BTreeMap::from([(U256::ZERO, U256::from(41))]),
+            ..Default::default()
+        };
+        let result = execute("60016000540160015500", &input);
+        assert!(result.success());
+        assert_eq!(result.storage[&U256::from(1)], U256::from(42));
+        assert_eq!(result.storage[&U256::ZERO], U256::from(41));
+    }
+
+    #[test]
+    fn calls_report_the_configured_outcome() {
+        // sstore(0, call(gas(), 0, 0, 0, 0, 0, 0))
+        let code = "5f5f5f5f5f5f5af1600055";
+        let result = execute(code, &Default::default());
+        assert_eq!(result.storage[&U256::ZERO], U256::from(1));
+
+        let mut input = ConcreteInput::default();
+        input.env.call_success = false;
+        let result = execute(code, &input);
+        assert_eq!(result.storage[&U256::ZERO], U256::ZERO);
+    }
+
+    #[test]
+    fn loops_run_out_of_steps() {
+        // jumpdest; push0; jump
+        let mut evm = ConcreteEvm::new(sym_handlers(Hardfork::default()));
+        evm.max_steps = 100;
+        let result = evm.execute(&hex::decode("5b5f56").unwrap(), &Default::default());
+        assert_eq!(result.halt, Some(HaltReason::OutOfGas));
+        assert_eq!(result.trace.len(), 100);
+    }
+
+    #[test]
+    fn huge_memory_runs_out_of_gas() {
+        // mstore(2**32, 1)
+        let result = execute("60016401000000005200", &Default::default());
+        assert_eq!(result.halt, Some(HaltReason::OutOfGas));
+        assert_eq!(result.halt_opcode, MSTORE);
+    }
+
+    #[test]
+    fn terms_over_constants_evaluate() {
+        let word = Term::word(U256::from(1) << 200);
+        let sum = Term::op(ADD, vec![word, Term::constant(1)]);
+        assert_eq!(
+            evaluate_term(&sum),
+            Some((U256::from(1) << 200) + U256::from(1))
+        );
+        let input = Term::op(CALLDATALOAD, vec![Term::constant(0)]);
+        assert_eq!(
+            evaluate_term(&Term::op(ADD, vec![input, Term::constant(1)])),
+            None
+        );
+    }
+}
@@ -0,0 +1,418 @@
+use anyhow::{anyhow, Context, Result};
+use ruint::aliases::U256;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::concrete::{ConcreteEvm, ConcreteInput, ExecutionResult};
+use crate::handlers::{HaltReason, HandlerType};
+use crate::opcodes::*;
+
+/// One test case in the VMTests layout of the Ethereum tests suite: `env`, `exec`, `pre`, and
+/// `out`/`post` when execution is expected to succeed. An optional `expect` object with `stack`
+/// (top last) and `memory` checks the final machine state as well, and an optional `opcode`
+/// names the opcode under test, by mnemonic or byte.
+#[derive(Debug, Clone)]
+pub struct Fixture {
+    /// File stem and test name, e.g. `arithmetic/add_small`
+    pub name: String,
+    /// The opcode under test, from the `opcode` field or else the start of the test name
+    pub opcode: Option<u8>,
+    pub code: Vec<u8>,
+    pub input: ConcreteInput,
+    pub expected: Expected,
+}
+
+/// What a fixture expects execution to end with
+#[derive(Debug, Clone, Default)]
+pub struct Expected {
+    /// False when the test has no `post` section, meaning an exceptional halt
+    pub success: bool,
+    pub out: Vec<u8>,
+    /// Non-zero storage slots of the executing account afterwards
+    pub storage: BTreeMap<U256, U256>,
+    pub stack: Option<Vec<U256>>,
+    pub memory: Option<Vec<u8>>,
+}
+
+/// Passed and failed tests of the opcodes in a group
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GroupTally {
+    pub passed: usize,
+    pub failed: usize,
+}
+
+/// A failed test and every difference found
+#[derive(Debug, Clone)]
+pub struct Failure {
+    pub name: String,
+    pub mismatches: Vec<String>,
+}
+
+/// Results of a conformance run. A test counts towards the group of the opcode it tests.
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub passed: usize,
+    pub groups: BTreeMap<&'static str, GroupTally>,
+    pub failures: Vec<Failure>,
+    /// Tests of opcodes the selected fork does not have yet, which are not run
+    pub skipped: Vec<String>,
+    /// Opcodes the handler table treated as undefined in tests that expected success
+    pub unsupported: BTreeMap<u8, usize>,
+}
+
+/// Load every fixture in a JSON file, or in all JSON files below a directory
+pub fn load(path: &Path) -> Result<Vec<Fixture>> {
+    let mut fixtures = Vec::new();
+    if path.is_dir() {
+        let mut entries: Vec<_> = fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<_>>()?;
+        entries.sort();
+        for entry in entries {
+            if entry.is_dir() || entry.extension().is_some_and(|ext| ext == "json") {
+                fixtures.extend(load(&entry)?);
+            }
+        }
+        return Ok(fixtures);
+    }
+
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let json: Value = serde_json::from_str(&fs::read_to_string(path)?)
+        .with_context(|| format!("Could not parse {}", path.display()))?;
+    let tests = json
+        .as_object()
+        .ok_or_else(|| anyhow!("{} is not an object of tests", path.display()))?;
+    for (name, test) in tests {
+        let name = format!("{}/{}", stem, name);
+        let fixture = parse_fixture(&name, test).with_context(|| format!("In test {}", name))?;
+        fixtures.push(fixture);
+    }
+    Ok(fixtures)
+}
+
+fn parse_fixture(name: &str, test: &Value) -> Result<Fixture> {
+    let exec = &test["exec"];
+    let env = &test["env"];
+    let address = word(&exec["address"])?;
+
+    let mut input = ConcreteInput {
+        calldata: bytes(&exec["data"])?,
+        ..Default::default()
+    };
+    input.env.address = address;
+    input.env.caller = word(&exec["caller"])?;
+    input.env.origin = word(&exec["origin"])?;
+    input.env.callvalue = word(&exec["value"])?;
+    input.env.gasprice = word(&exec["gasPrice"])?;
+    input.env.gas = word(&exec["gas"])?;
+    input.env.coinbase = word(&env["currentCoinbase"])?;
+    input.env.timestamp = word(&env["currentTimestamp"])?;
+    input.env.number = word(&env["currentNumber"])?;
+    input.env.gaslimit = word(&env["currentGasLimit"])?;
+    // Difficulty became PREVRANDAO at the merge, fixtures use either name
+    input.env.prevrandao = match &env["currentRandom"] {
+        Value::Null => word(&env["currentDifficulty"])?,
+        random => word(random)?,
+    };
+    if !env["currentBaseFee"].is_null() {
+        input.env.basefee = word(&env["currentBaseFee"])?;
+    }
+
+    if let Some(account) = find_account(&test["pre"], address) {
+        input.env.balance = word(&account["balance"])?;
+        input.storage = storage(&account["storage"])?;
+    }
+
+    let mut expected = Expected {
+        success: !test["post"].is_null(),
+        out: bytes(&test["out"])?,
+        ..Default::default()
+    };
+    if let Some(account) = find_account(&test["post"], address) {
+        expected.storage = storage(&account["storage"])?;
+    }
+    if let Some(stack) = test["expect"]["stack"].as_array() {
+        expected.stack = Some(stack.iter().map(word).collect::<Result<_>>()?);
+    }
+    if !test["expect"]["memory"].is_null() {
+        expected.memory = Some(bytes(&test["expect"]["memory"])?);
+    }
+
+    let opcode = match test["opcode"].as_str() {
+        Some(text) => Some(parse_opcode(text).ok_or_else(|| anyhow!("{} is not an opcode", text))?),
+        None => opcode_of_name(name),
+    };
+
+    Ok(Fixture {
+        name: name.to_string(),
+        opcode,
+        code: bytes(&exec["code"])?,
+        input,
+        expected,
+    })
+}
+
+// An opcode by mnemonic, in any case, or by byte
+fn parse_opcode(text: &str) -> Option<u8> {
+    match text.strip_prefix("0x") {
+        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+        None => (0..=u8::MAX).find(|&opcode| {
+            opcode_name(opcode) != "UNKNOWN" && opcode_name(opcode).eq_ignore_ascii_case(text)
+        }),
+    }
+}
+
+// The opcode a test name starts with, as in `sha3_empty` or `add0`
+fn opcode_of_name(name: &str) -> Option<u8> {
+    let test = name.rsplit('/').next().unwrap_or(name);
+    let word = test.split('_').next().unwrap_or(test);
+    parse_opcode(word).or_else(|| parse_opcode(word.trim_end_matches(|c: char| c.is_ascii_digit())))
+}
+
+// Accounts are keyed by address, in whatever case and padding the fixture uses
+fn find_account(accounts: &Value, address: U256) -> Option<&Value> {
+    accounts
+        .as_object()?
+        .iter()
+        .find(|(key, _)| parse_word(key).ok() == Some(address))
+        .map(|(_, account)| account)
+}
+
+fn storage(slots: &Value) -> Result<BTreeMap<U256, U256>> {
+    let mut storage = BTreeMap::new();
+    if let Some(slots) = slots.as_object() {
+        for (slot, value) in slots {
+            let value = word(value)?;
+            if !value.is_zero() {
+                storage.insert(parse_word(slot)?, value);
+            }
+        }
+    }
+    Ok(storage)
+}
+
+fn word(value: &Value) -> Result<U256> {
+    match value {
+        Value::Null => Ok(U256::ZERO),
+        Value::String(text) => parse_word(text),
+        Value::Number(number) => number
+            .as_u64()
+            .map(U256::from)
+            .ok_or_else(|| anyhow!("{} is not a word", number)),
+        _ => Err(anyhow!("{} is not a word", value)),
+    }
+}
+
+fn parse_word(text: &str) -> Result<U256> {
+    match text.strip_prefix("0x") {
+        Some("") => Ok(U256::ZERO),
+        Some(hex) => Ok(U256::from_str_radix(hex, 16)?),
+        None => Ok(U256::from_str_radix(text, 10)?),
+    }
+}
+
+fn bytes(value: &Value) -> Result<Vec<u8>> {
+    let text = value.as_str().unwrap_or("");
+    Ok(hex::decode(text.trim_start_matches("0x"))?)
+}
+
+/// Differences between what the fixture expects and what execution did
+pub fn compare(expected: &Expected, result: &ExecutionResult) -> Vec<String> {
+    let mut mismatches = Vec::new();
+    if expected.success != result.success() {
+        let status = match &result.halt {
+            Some(halt) => format!("{:?}", halt),
+            None => opcode_name(result.halt_opcode).to_string(),
+        };
+        mismatches.push(format!(
+            "expected {} but execution ended with {} at {:#x}",
+            if expected.success {
+                "success"
+            } else {
+                "an exceptional halt"
+            },
+            status,
+            result.halt_pc
+        ));
+        return mismatches;
+    }
+
+    if expected.success {
+        if expected.out != result.return_data {
+            mismatches.push(format!(
+                "out: expected 0x{} got 0x{}",
+                hex::encode(&expected.out),
+                hex::encode(&result.return_data)
+            ));
+        }
+        let slots: BTreeSet<&U256> = expected
+            .storage
+            .keys()
+            .chain(result.storage.keys())
+            .collect();
+        for slot in slots {
+            let want = expected.storage.get(slot).copied().unwrap_or_default();
+            let got = result.storage.get(slot).copied().unwrap_or_default();
+            if want != got {
+                mismatches.push(format!(
+                    "storage {:#x}: expected {:#x} got {:#x}",
+                    slot, want, got
+                ));
+            }
+        }
+    }
+
+    if let Some(stack) = &expected.stack {
+        if *stack != result.stack {
+            let render = |stack: &[U256]| {
+                let items: Vec<String> = stack.iter().map(|v| format!("{:#x}", v)).collect();
+                format!("[{}]", items.join(", "))
+            };
+            mismatches.push(format!(
+                "stack: expected {} got {}",
+                render(stack),
+                render(&result.stack)
+            ));
+        }
+    }
+    if let Some(memory) = &expected.memory {
+        if *memory != result.memory {
+            mismatches.push(format!(
+                "memory: expected 0x{} got 0x{}",
+                hex::encode(memory),
+                hex::encode(&result.memory)
+            ));
+        }
+    }
+    mismatches
+}
+
+/// Run every fixture through the concrete interpreter and tally the results per group of the
+/// opcode under test. Tests of opcodes the handler table treats as undefined, but which some fork
+/// defines, are skipped: they test a later fork than the selected one.
+pub fn run(evm: &ConcreteEvm, fixtures: &[Fixture]) -> Report {
+    let mut report = Report::default();
+    for fixture in fixtures {
+        if let Some(opcode) = fixture.opcode {
+            let undefined = evm.handlers[opcode as usize].handler_type == HandlerType::Invalid;
+            if undefined && opcode != INVALID && opcode_name(opcode) != "UNKNOWN" {
+                report.skipped.push(fixture.name.clone());
+                continue;
+            }
+        }
+
+        let result = evm.execute(&fixture.code, &fixture.input);
+        let mismatches = compare(&fixture.expected, &result);
+        let passed = mismatches.is_empty();
+
+        let group = fixture.opcode.map_or("unattributed", opcode_group);
+        let tally = report.groups.entry(group).or_default();
+        match passed {
+            true => tally.passed += 1,
+            false => tally.failed += 1,
+        }
+
+        if let Some(HaltReason::InvalidOpcode(opcode)) = result.halt {
+            if fixture.expected.success {
+                *report.unsupported.entry(opcode).or_default() += 1;
+            }
+        }
+
+        match passed {
+            true => report.passed += 1,
+            false => report.failures.push(Failure {
+                name: fixture.name.clone(),
+                mismatches,
+            }),
+        }
+    }
+    report
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<32} {:>6} {:>6}", "group", "passed", "failed")?;
+        for (group, tally) in &self.groups {
+            writeln!(f, "{:<32} {:>6} {:>6}", group, tally.passed, tally.failed)?;
+        }
+        for (opcode, count) in &self.unsupported {
+            writeln!(
+                f,
+                "UNSUPPORTED: {} ({:#04x}) in {} tests",
+                opcode_name(*opcode),
+                opcode,
+                count
+            )?;
+        }
+        for name in &self.skipped {
+            writeln!(f, "SKIP {}", name)?;
+        }
+        for failure in &self.failures {
+            writeln!(f, "FAIL {}", failure.name)?;
+            for mismatch in &failure.mismatches {
+                writeln!(f, "    {}", mismatch)?;
+            }
+        }
+        write!(f, "{} passed, {} failed", self.passed, self.failures.len())?;
+        if !self.skipped.is_empty() {
+            write!(
+                f,
+                ", {} skipped for opcodes the fork does not have",
+                self.skipped.len()
+            )?;
+        }
+        writeln!(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::sym_handlers;
+    use crate::hardfork::Hardfork;
+
+    fn fixtures() -> Vec<Fixture> {
+        load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/conformance")).unwrap()
+    }
+
+    #[test]
+    fn tests_name_their_opcode() {
+        assert_eq!(opcode_of_name("arithmetic/add0"), Some(ADD));
+        assert_eq!(opcode_of_name("log/log0_one_word"), Some(LOG0));
+        assert_eq!(opcode_of_name("sha3/sha3_empty"), Some(SHA3));
+        assert_eq!(opcode_of_name("system/implicit_stop"), None);
+        assert_eq!(parse_opcode("0x0c"), Some(0x0c));
+        assert_eq!(parse_opcode("mstore8"), Some(MSTORE8));
+    }
+
+    #[test]
+    fn bundled_fixtures_pass() {
+        let fixtures = fixtures();
+        let evm = ConcreteEvm::new(sym_handlers(Hardfork::default()));
+        let report = run(&evm, &fixtures);
+        assert!(report.failures.is_empty(), "{}", report);
+        assert!(report.skipped.is_empty());
+        assert_eq!(report.passed, fixtures.len());
+        assert_eq!(report.groups["arithmetic"].passed, 36);
+    }
+
+    #[test]
+    fn tests_of_later_forks_are_skipped() {
+        let evm = ConcreteEvm::new(sym_handlers(Hardfork::Frontier));
+        let report = run(&evm, &fixtures());
+        assert!(report.failures.is_empty(), "{}", report);
+        assert!(report
+            .skipped
+            .contains(&"push_dup_swap/push0_zero".to_string()));
+        assert!(!report
+            .skipped
+            .contains(&"system/invalid_designated".to_string()));
+        assert_eq!(report.groups["undefined"].passed, 1);
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::cfg::{instructions, Cfg};
+use crate::explorer::PathResult;
+use crate::hardfork::Hardfork;
+use crate::opcodes::*;
+use crate::solve::DLResult;
+
+/// What the explorer learnt about one direction of a JUMPI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DirectionStatus {
+    /// No path ever went this way
+    Unexplored,
+    /// Every path going this way was proven unsatisfiable
+    Infeasible,
+    /// The solver could not decide any path going this way
+    Unknown,
+    /// Some satisfiable path goes this way
+    Feasible,
+}
+
+/// Coverage of one JUMPI, per direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BranchCoverage {
+    pub taken: DirectionStatus,
+    pub not_taken: DirectionStatus,
+}
+
+/// Coverage of one basic block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockCoverage {
+    pub start: usize,
+    pub end: usize,
+    pub instructions: usize,
+    /// Instructions of the block executed on some path that is not known to be infeasible
+    pub covered: usize,
+}
+
+/// Instruction and branch coverage of the explored paths over a runtime bytecode
+#[derive(Debug, Clone)]
+pub struct Coverage {
+    code: Vec<u8>,
+    /// Pcs executed on some path that is not known to be infeasible
+    pub executed: BTreeSet<usize>,
+    /// Every JUMPI in the code, keyed by pc
+    pub branches: BTreeMap<usize, BranchCoverage>,
+    pub blocks: Vec<BlockCoverage>,
+}
+
+impl Coverage {
+    /// Gather coverage from the explored paths. Paths proven unsatisfiable never happen on chain,
+    /// so they only count towards marking a JUMPI direction infeasible. Blocks are split with the
+    /// opcodes `fork` defines.
+    pub fn new(code: &[u8], results: &[PathResult], fork: Hardfork) -> Self {
+        let mut executed = BTreeSet::new();
+        let mut branches: BTreeMap<usize, BranchCoverage> = instructions(code)
+            .into_iter()
+            .filter(|&(_, opcode)| opcode == JUMPI)
+            .map(|(pc, _)| {
+                let unexplored = BranchCoverage {
+                    taken: DirectionStatus::Unexplored,
+                    not_taken: DirectionStatus::Unexplored,
+                };
+                (pc, unexplored)
+            })
+            .collect();
+
+        for result in results {
+            let status = match result.result {
+                DLResult::Sat => DirectionStatus::Feasible,
+                DLResult::Unsat => DirectionStatus::Infeasible,
+                DLResult::Unknown => DirectionStatus::Unknown,
+            };
+            if result.result != DLResult::Unsat {
+                executed.extend(result.path.iter().copied());
+            }
+            for branch in &result.branches {
+                if let Some(coverage) = branches.get_mut(&branch.pc) {
+                    let direction = match branch.taken {
+                        true => &mut coverage.taken,
+                        false => &mut coverage.not_taken,
+                    };
+                    // Feasible beats unknown beats infeasible beats unexplored
+                    *direction = (*direction).max(status);
+                }
+            }
+        }
+
+        let blocks = Cfg::new(code, fork)
+            .blocks
+            .values()
+            .map(|block| BlockCoverage {
+                start: block.start,
+                end: block.end,
+                instructions: block.instructions.len(),
+                covered: block
+                    .instructions
+                    .iter()
+                    .filter(|(pc, _)| executed.contains(pc))
+                    .count(),
+            })
+            .collect();
+
+        Self {
+            code: code.to_vec(),
+            executed,
+            branches,
+            blocks,
+        }
+    }
+
+    /// (covered, total) instructions
+    pub fn instruction_totals(&self) -> (usize, usize) {
+        self.blocks.iter().fold((0, 0), |(covered, total), block| {
+            (covered + block.covered, total + block.instructions)
+        })
+    }
+
+    /// (feasible, total) JUMPI directions
+    pub fn branch_totals(&self) -> (usize, usize) {
+        let feasible = self
+            .branches
+            .values()
+            .flat_map(|branch| [branch.taken, branch.not_taken])
+            .filter(|&status| status == DirectionStatus::Feasible)
+            .count();
+        (feasible, self.branches.len() * 2)
+    }
+
+    /// Disassembly with each instruction marked as covered or not, and JUMPIs annotated with what
+    /// is known about each direction
+    pub fn annotated_disassembly(&self) -> String {
+        let mut out = String::new();
+        for (pc, opcode) in instructions(&self.code) {
+            let mark = if self.executed.contains(&pc) {
+                '*'
+            } else {
+                ' '
+            };
+            let mut line = format!("{} {:#06x}  {}", mark, pc, opcode_name(opcode));
+
+            let size = push_size(opcode);
+            if size > 0 {
+                let end = (pc + 1 + size).min(self.code.len());
+                line.push_str(&format!(" 0x{}", hex::encode(&self.code[pc + 1..end])));
+            }
+            if let Some(branch) = self.branches.get(&pc) {
+                line.push_str(&format!(
+                    "    ; taken: {:?}, not taken: {:?}",
+                    branch.taken, branch.not_taken
+                ));
+            }
+            out.push_str(&line);
+            out.push('\n');
+        }
+        out
+    }
+}
+
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (covered, total) = self.instruction_totals();
+        writeln!(f, "instructions: {}/{} covered", covered, total)?;
+        let (feasible, directions) = self.branch_totals();
+        writeln!(f, "branch directions: {}/{} feasible", feasible, directions)?;
+        writeln!(f)?;
+
+        for block in &self.blocks {
+            writeln!(
+                f,
+                "block {:#x}..={:#x}: {}/{}",
+                block.start, block.end, block.covered, block.instructions
+            )?;
+        }
+        writeln!(f)?;
+        write!(f, "{}", self.annotated_disassembly())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitblast::BvSolver;
+    use crate::explorer::Explorer;
+    use crate::handlers::{sym_handlers, EvmContext};
+
+    // x = calldataload(0); if (x == 10) { if (x == 11) stop } stop
+    const NESTED: &str = "60003580600a14600b57005b600b14601357005b00";
+
+    fn coverage(code: &str) -> Coverage {
+        let code = hex::decode(code).unwrap();
+        let mut explorer = Explorer::new(sym_handlers(Hardfork::default()));
+        explorer.solver = Box::new(BvSolver::new(256));
+        explorer.run(&mut EvmContext::new(code.clone()));
+        Coverage::new(&code, &explorer.results, Hardfork::default())
+    }
+
+    #[test]
+    fn contradicting_branches_are_infeasible() {
+        let coverage = coverage(NESTED);
+        let outer = coverage.branches[&0x9];
+        assert_eq!(outer.taken, DirectionStatus::Feasible);
+        assert_eq!(outer.not_taken, DirectionStatus::Feasible);
+        let inner = coverage.branches[&0x11];
+        assert_eq!(inner.taken, DirectionStatus::Infeasible);
+        assert_eq!(inner.not_taken, DirectionStatus::Feasible);
+        assert_eq!(coverage.branch_totals(), (3, 4));
+    }
+
+    #[test]
+    fn infeasible_paths_cover_nothing() {
+        let coverage = coverage(NESTED);
+        assert!(!coverage.executed.contains(&0x13));
+        assert!(!coverage.executed.contains(&0x14));
+        assert_eq!(coverage.instruction_totals(), (14, 16));
+        let last = coverage.blocks.last().unwrap();
+        assert_eq!((last.start, last.covered), (0x13, 0));
+
+        let disassembly = coverage.annotated_disassembly();
+        assert!(disassembly.contains("* 0x0000  PUSH1 0x00\n"));
+        assert!(disassembly.contains("    ; taken: Infeasible, not taken: Feasible\n"));
+        assert!(disassembly.ends_with("  0x0014  STOP\n"));
+    }
+
+    #[test]
+    fn unexplored_code_has_unexplored_branches() {
+        let code = hex::decode(NESTED).unwrap();
+        let coverage = Coverage::new(&code, &[], Hardfork::default());
+        assert_eq!(coverage.branches.len(), 2);
+        assert!(coverage.branches.values().all(|branch| {
+            branch.taken == DirectionStatus::Unexplored
+                && branch.not_taken == DirectionStatus::Unexplored
+        }));
+        assert_eq!(coverage.instruction_totals(), (0, 16));
+    }
+}
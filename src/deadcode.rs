@@ -0,0 +1,206 @@
+use std::fmt;
+
+use crate::cfg::Cfg;
+use crate::coverage::{Coverage, DirectionStatus};
+use crate::explorer::PathResult;
+use crate::hardfork::Hardfork;
+
+/// A JUMPI that every feasible path reaching it decides the same way
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConstantBranch {
+    pub pc: usize,
+    /// Whether the jump is always taken, as opposed to never
+    pub taken: bool,
+    /// What is known about the other direction: infeasible, or never tried because the
+    /// condition was a constant
+    pub other: DirectionStatus,
+}
+
+/// Why a block is dead
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadReason {
+    /// No jump or fallthrough in the control flow graph leads to it
+    Unreachable,
+    /// Edges lead to it, but only along paths proven infeasible, or along none at all
+    Infeasible,
+}
+
+/// A basic block that no feasible path executes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeadBlock {
+    pub start: usize,
+    pub end: usize,
+    pub instructions: usize,
+    pub reason: DeadReason,
+}
+
+/// Code the explored paths prove dead: constant JUMPIs and blocks no feasible path reaches.
+/// Directions the solver could not decide count as live, so everything listed is dead for sure
+/// as far as the exploration went.
+#[derive(Debug, Clone, Default)]
+pub struct DeadCode {
+    pub branches: Vec<ConstantBranch>,
+    pub blocks: Vec<DeadBlock>,
+}
+
+impl DeadCode {
+    pub fn new(code: &[u8], results: &[PathResult], fork: Hardfork) -> Self {
+        let coverage = Coverage::new(code, results, fork);
+        let cfg = Cfg::new(code, fork);
+
+        let dead = |status: DirectionStatus| {
+            matches!(
+                status,
+                DirectionStatus::Infeasible | DirectionStatus::Unexplored
+            )
+        };
+        let branches = coverage
+            .branches
+            .iter()
+            .filter_map(|(&pc, branch)| match (branch.taken, branch.not_taken) {
+                (DirectionStatus::Feasible, other) if dead(other) => Some(ConstantBranch {
+                    pc,
+                    taken: true,
+                    other,
+                }),
+                (other, DirectionStatus::Feasible) if dead(other) => Some(ConstantBranch {
+                    pc,
+                    taken: false,
+                    other,
+                }),
+                _ => None,
+            })
+            .collect();
+
+        // Without every jump target resolved the graph may miss edges, so it proves nothing
+        let reachable = cfg.reachable();
+        let complete = cfg.unresolved_jumps().is_empty();
+        let blocks = coverage
+            .blocks
+            .iter()
+            .filter(|block| block.covered == 0)
+            .map(|block| DeadBlock {
+                start: block.start,
+                end: block.end,
+                instructions: block.instructions,
+                reason: match complete && !reachable.contains(&block.start) {
+                    true => DeadReason::Unreachable,
+                    false => DeadReason::Infeasible,
+                },
+            })
+            .collect();
+
+        Self { branches, blocks }
+    }
+
+    /// Number of instructions in dead blocks
+    pub fn dead_instructions(&self) -> usize {
+        self.blocks.iter().map(|block| block.instructions).sum()
+    }
+}
+
+impl fmt::Display for DeadCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for branch in &self.branches {
+            writeln!(
+                f,
+                "JUMPI {:#x}: {}, the {} side is {}",
+                branch.pc,
+                if branch.taken {
+                    "always taken"
+                } else {
+                    "never taken"
+                },
+                if branch.taken { "not-taken" } else { "taken" },
+                match branch.other {
+                    DirectionStatus::Unexplored => "never possible, its condition is a constant",
+                    _ => "infeasible",
+                }
+            )?;
+        }
+        for block in &self.blocks {
+            writeln!(
+                f,
+                "block {:#x}..={:#x}: {} instructions, {}",
+                block.start,
+                block.end,
+                block.instructions,
+                match block.reason {
+                    DeadReason::Unreachable => "no jump or fallthrough leads here",
+                    DeadReason::Infeasible => "no feasible path reaches it",
+                }
+            )?;
+        }
+        writeln!(
+            f,
+            "{} constant branches, {} dead blocks with {} instructions",
+            self.branches.len(),
+            self.blocks.len(),
+            self.dead_instructions()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitblast::BvSolver;
+    use crate::explorer::Explorer;
+    use crate::handlers::{sym_handlers, EvmContext};
+
+    fn dead_code(code: &str) -> DeadCode {
+        let code = hex::decode(code).unwrap();
+        let mut explorer = Explorer::new(sym_handlers(Hardfork::default()));
+        explorer.solver = Box::new(BvSolver::new(256));
+        explorer.run(&mut EvmContext::new(code.clone()));
+        DeadCode::new(&code, &explorer.results, Hardfork::default())
+    }
+
+    #[test]
+    fn contradicting_branches_leave_dead_blocks() {
+        // x = calldataload(0); if (x == 10) { if (x == 11) stop } stop
+        let dead = dead_code("60003580600a14600b57005b600b14601357005b00");
+        assert_eq!(
+            dead.branches,
+            vec![ConstantBranch {
+                pc: 0x11,
+                taken: false,
+                other: DirectionStatus::Infeasible,
+            }]
+        );
+        assert_eq!(
+            dead.blocks,
+            vec![DeadBlock {
+                start: 0x13,
+                end: 0x14,
+                instructions: 2,
+                reason: DeadReason::Infeasible,
+            }]
+        );
+        assert!(dead
+            .to_string()
+            .starts_with("JUMPI 0x11: never taken, the taken side is infeasible\n"));
+    }
+
+    #[test]
+    fn constant_conditions_never_try_the_other_side() {
+        // if (1) jump
+        let dead = dead_code("6001600657005b00");
+        assert_eq!(dead.branches.len(), 1);
+        assert!(dead.branches[0].taken);
+        assert_eq!(dead.branches[0].other, DirectionStatus::Unexplored);
+        assert_eq!(dead.blocks.len(), 1);
+        assert_eq!(dead.blocks[0].start, 0x5);
+    }
+
+    #[test]
+    fn code_after_a_halt_is_unreachable() {
+        let dead = dead_code("0060016002");
+        assert_eq!(dead.blocks.len(), 1);
+        assert_eq!(dead.blocks[0].reason, DeadReason::Unreachable);
+        assert_eq!(dead.dead_instructions(), 2);
+        assert!(dead
+            .to_string()
+            .ends_with("0 constant branches, 1 dead blocks with 2 instructions\n"));
+    }
+}
@@ -0,0 +1,181 @@
+use ruint::aliases::U256;
+use std::fmt;
+
+use crate::cfg::{instructions, push_value};
+use crate::concrete::evaluate_term;
+use crate::opcodes::*;
+use crate::sym_stack::{is_concrete, Term};
+
+/// A public function found in the dispatcher
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Function {
+    pub selector: [u8; 4],
+    /// Pc the dispatcher jumps to for this selector
+    pub entry: usize,
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{} at {:#x}", hex::encode(self.selector), self.entry)
+    }
+}
+
+/// Functions of a Solidity dispatcher, in the order it checks them. The dispatcher compares the
+/// selector (see `is_selector`) with each one and jumps to the function on a match, which solc
+/// emits as either of
+///
+/// `DUP1 PUSH4 <selector> EQ PUSH <entry> JUMPI`
+/// `PUSH4 <selector> DUP2 EQ PUSH <entry> JUMPI`
+pub fn functions(code: &[u8]) -> Vec<Function> {
+    let instructions = instructions(code);
+    let mut found: Vec<Function> = Vec::new();
+    for window in instructions.windows(5) {
+        let opcodes: Vec<u8> = window.iter().map(|&(_, opcode)| opcode).collect();
+        let (selector_pc, target_pc) = match opcodes.as_slice() {
+            [DUP1, PUSH4, EQ, PUSH1..=PUSH4, JUMPI] => (window[1].0, window[3].0),
+            [PUSH4, DUP2, EQ, PUSH1..=PUSH4, JUMPI] => (window[0].0, window[3].0),
+            _ => continue,
+        };
+        let (selector, entry) = match (push_value(code, selector_pc), push_value(code, target_pc)) {
+            (Some(selector), Some(entry)) => ((selector as u32).to_be_bytes(), entry as usize),
+            _ => continue,
+        };
+        if !found.iter().any(|function| function.selector == selector) {
+            found.push(Function { selector, entry });
+        }
+    }
+    found
+}
+
+/// Whether `term` is `lt(calldatasize(), n)` for an `n` of at most 4, the dispatcher's check that
+/// there is no selector to dispatch on
+pub fn is_short_calldata_check(term: &Term) -> bool {
+    match (term.sym_val.value as u8, term.args.as_slice()) {
+        (LT, [size, n]) if !is_concrete(term) => {
+            !is_concrete(size)
+                && size.sym_val.value as u8 == CALLDATASIZE
+                && is_concrete(n)
+                && n.sym_val.value <= 4
+        }
+        _ => false,
+    }
+}
+
+/// Whether `term` is the selector the dispatcher compares: `shr(224, calldataload(0))`, or
+/// `div(calldataload(0), 2**224)` from compilers that predate the shift opcodes
+pub fn is_selector(term: &Term) -> bool {
+    match (term.sym_val.value as u8, term.args.as_slice()) {
+        _ if is_concrete(term) => false,
+        (SHR, [shift, value]) => {
+            is_concrete(shift) && shift.sym_val.value == 224 && is_calldata_start(value)
+        }
+        (DIV, [value, divisor]) => {
+            is_calldata_start(value) && evaluate_term(divisor) == Some(U256::from(1) << 224)
+        }
+        _ => false,
+    }
+}
+
+// Whether `term` is `calldataload(0)`
+fn is_calldata_start(term: &Term) -> bool {
+    !is_concrete(term)
+        && term.sym_val.value as u8 == CALLDATALOAD
+        && term
+            .args
+            .first()
+            .is_some_and(|offset| is_concrete(offset) && offset.sym_val.value == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::explorer::Explorer;
+    use crate::handlers::{sym_handlers, EvmContext};
+    use crate::hardfork::Hardfork;
+
+    // if (calldatasize() < 4) goto fallback; selector := shr(224, calldataload(0));
+    // if (selector == 0xaabbccdd) goto function; fallback: stop; function: sstore(0, 1)
+    const DISPATCHER: &str = "600436106016575f3560e01c8063aabbccdd146018575b005b600160005500";
+
+    // if (calldatasize() < 4) goto fallback; selector := and(div(calldataload(0), 2**224),
+    // 0xffffffff); if (selector == 0xaabbccdd) goto function; fallback: stop; function:
+    // sstore(0, 1), as compilers before the shift opcodes emit it
+    const DIV_DISPATCHER: &str = concat!(
+        "600436106039577c01",
+        "00000000000000000000000000000000000000000000000000000000",
+        "60003504",
+        "63ffffffff16",
+        "8063aabbccdd14603b57",
+        "5b00",
+        "5b600160005500"
+    );
+
+    fn calldata_start() -> Term {
+        Term::op(CALLDATALOAD, vec![Term::constant(0)])
+    }
+
+    #[test]
+    fn selectors_are_shifted_or_divided_out() {
+        let shifted = Term::op(SHR, vec![Term::constant(224), calldata_start()]);
+        assert!(is_selector(&shifted));
+        let divided = Term::op(
+            DIV,
+            vec![calldata_start(), Term::word(U256::from(1) << 224)],
+        );
+        assert!(is_selector(&divided));
+
+        let other = Term::op(SHR, vec![Term::constant(224), Term::constant(0)]);
+        assert!(!is_selector(&other));
+        let other = Term::op(SHR, vec![Term::constant(200), calldata_start()]);
+        assert!(!is_selector(&other));
+        let other = Term::op(DIV, vec![calldata_start(), Term::constant(2)]);
+        assert!(!is_selector(&other));
+    }
+
+    #[test]
+    fn functions_of_the_dispatcher_are_found() {
+        let code = hex::decode(DISPATCHER).unwrap();
+        assert_eq!(
+            functions(&code),
+            vec![Function {
+                selector: [0xaa, 0xbb, 0xcc, 0xdd],
+                entry: 0x18,
+            }]
+        );
+    }
+
+    #[test]
+    fn functions_of_a_div_dispatcher_are_found() {
+        let code = hex::decode(DIV_DISPATCHER).unwrap();
+        assert_eq!(
+            functions(&code),
+            vec![Function {
+                selector: [0xaa, 0xbb, 0xcc, 0xdd],
+                entry: 0x3b,
+            }]
+        );
+    }
+
+    #[test]
+    fn the_dispatcher_goes_straight_to_the_function() {
+        for dispatcher in [DISPATCHER, DIV_DISPATCHER] {
+            let code = hex::decode(dispatcher).unwrap();
+            let function = functions(&code)[0];
+            let mut explorer = Explorer::new(sym_handlers(Hardfork::default()));
+            explorer.run_function(&mut EvmContext::new(code), &function, &[]);
+            assert_eq!(explorer.results.len(), 1);
+            assert!(explorer.results[0].path.contains(&function.entry));
+        }
+    }
+
+    #[test]
+    fn short_calldata_checks_compare_the_size_with_four() {
+        let size = Term::op(CALLDATASIZE, Vec::new());
+        let check = Term::op(LT, vec![size.clone(), Term::constant(4)]);
+        assert!(is_short_calldata_check(&check));
+        assert!(!is_short_calldata_check(&Term::op(
+            LT,
+            vec![size, Term::constant(36)]
+        )));
+    }
+}
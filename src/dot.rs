@@ -0,0 +1,151 @@
+use crate::opcodes::*;
+use crate::solve::DLResult;
+use crate::sym_stack::Term;
+
+/// Node of the symbolic execution tree
+#[derive(Debug, Clone)]
+enum Node {
+    /// A JUMPI where the explorer forked
+    Fork { pc: usize, condition: String },
+    /// The end of a path
+    Leaf {
+        pc: usize,
+        opcode: u8,
+        result: DLResult,
+    },
+}
+
+/// Tree of the forks the explorer made, rendered as Graphviz DOT
+#[derive(Debug, Clone, Default)]
+pub struct DotTree {
+    nodes: Vec<Node>,
+    /// (parent, child, taken)
+    edges: Vec<(usize, usize, bool)>,
+}
+
+/// Position in the tree a path is at: the last fork and the direction taken there
+pub type TreeParent = Option<(usize, bool)>;
+
+impl DotTree {
+    /// Add a fork at a JUMPI below `parent`, returning its node id
+    pub fn fork(&mut self, parent: TreeParent, pc: usize, condition: &Term) -> usize {
+        self.add(
+            parent,
+            Node::Fork {
+                pc,
+                condition: condition.to_string(),
+            },
+        )
+    }
+
+    /// Add the end of a path below `parent`, returning its node id
+    pub fn leaf(&mut self, parent: TreeParent, pc: usize, opcode: u8, result: DLResult) -> usize {
+        self.add(parent, Node::Leaf { pc, opcode, result })
+    }
+
+    fn add(&mut self, parent: TreeParent, node: Node) -> usize {
+        let id = self.nodes.len();
+        self.nodes.push(node);
+        if let Some((parent, taken)) = parent {
+            self.edges.push((parent, id, taken));
+        }
+        id
+    }
+
+    /// Render the tree in the DOT language
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph exploration {\n    node [fontname=\"monospace\"];\n");
+
+        for (id, node) in self.nodes.iter().enumerate() {
+            match node {
+                Node::Fork { pc, condition } => out.push_str(&format!(
+                    "    n{} [shape=box, label=\"JUMPI @ {:#x}\\n{}\"];\n",
+                    id,
+                    pc,
+                    escape(condition)
+                )),
+                Node::Leaf { pc, opcode, result } => {
+                    let fill = match result {
+                        DLResult::Sat => "palegreen",
+                        DLResult::Unsat => "lightcoral",
+                        DLResult::Unknown => "lightgrey",
+                    };
+                    // The outline tells apart how the path ended
+                    let outline = match *opcode {
+                        STOP | RETURN => "black",
+                        REVERT => "orange",
+                        SELFDESTRUCT => "purple",
+                        _ => "red",
+                    };
+                    out.push_str(&format!(
+                        "    n{} [shape=ellipse, style=\"filled,bold\", fillcolor={}, color={}, label=\"{} @ {:#x}\\n{:?}\"];\n",
+                        id,
+                        fill,
+                        outline,
+                        opcode_name(*opcode),
+                        pc,
+                        result
+                    ));
+                }
+            }
+        }
+
+        for (parent, child, taken) in &self.edges {
+            out.push_str(&format!(
+                "    n{} -> n{} [label=\"{}\"];\n",
+                parent,
+                child,
+                if *taken { "taken" } else { "not taken" }
+            ));
+        }
+
+        out.push_str("}\n");
+        out
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sym_stack::{Kind, SymVal};
+
+    fn op(opcode: u8, args: Vec<Term>) -> Term {
+        Term {
+            sym_val: SymVal {
+                value: opcode as u64,
+                kind: Kind::Symbolic,
+            },
+            args,
+        }
+    }
+
+    #[test]
+    fn forks_and_leaves_are_linked_by_direction() {
+        let mut tree = DotTree::default();
+        let condition = op(ISZERO, vec![op(CALLVALUE, Vec::new())]);
+        let root = tree.fork(None, 0x7, &condition);
+        tree.leaf(Some((root, true)), 0xa, STOP, DLResult::Sat);
+        tree.leaf(Some((root, false)), 0x9, REVERT, DLResult::Unsat);
+
+        let dot = tree.to_dot();
+        assert!(dot.starts_with("digraph exploration {\n"));
+        assert!(dot.contains(&format!(
+            "    n0 [shape=box, label=\"JUMPI @ 0x7\\n{}\"];\n",
+            condition
+        )));
+        assert!(dot.contains("fillcolor=palegreen, color=black, label=\"STOP @ 0xa\\nSat\""));
+        assert!(dot.contains("fillcolor=lightcoral, color=orange, label=\"REVERT @ 0x9\\nUnsat\""));
+        assert!(dot.contains("    n0 -> n1 [label=\"taken\"];\n"));
+        assert!(dot.contains("    n0 -> n2 [label=\"not taken\"];\n"));
+        assert!(dot.ends_with("}\n"));
+    }
+
+    #[test]
+    fn labels_are_escaped() {
+        assert_eq!(escape(r#"a"b\c"#), r#"a\"b\\c"#);
+    }
+}
@@ -0,0 +1,443 @@
+use ruint::aliases::U256;
+
+use crate::concrete::{arithmetic, evaluate_term, ConcreteEvm, STACK_LIMIT};
+use crate::dispatcher::{is_selector, is_short_calldata_check, Function};
+use crate::dot::DotTree;
+use crate::failures::classify;
+use crate::handlers::*;
+use crate::opcodes::*;
+use crate::overflow::{is_checked, wrap_condition, Overflow};
+use crate::replay::{replay, Replay};
+use crate::solve::{branch_to_expressions, DLResult, DlSolver, Model, Solver};
+use crate::sym_stack::{Expr, Kind, SymVal, Term};
+
+/// Outcome of one fully explored path
+#[derive(Debug, Clone)]
+pub struct PathResult {
+    /// JUMPI decisions along the path, in order
+    pub branches: Vec<Branch>,
+    /// Pcs of every executed instruction
+    pub path: Vec<usize>,
+    /// DL constraints collected for the path
+    pub constraints: Vec<Expr>,
+    /// Whether the path condition is satisfiable
+    pub result: DLResult,
+    /// Assignment satisfying the path condition, when it is satisfiable
+    pub model: Option<Model>,
+    /// Opcode that ended the path
+    pub halt_opcode: u8,
+    /// Pc of the instruction that ended the path
+    pub halt_pc: usize,
+    /// Set when the path ended with an exceptional halt
+    pub halt: Option<HaltReason>,
+    /// Outcome of replaying the model concretely, when replays are enabled and there is a model
+    pub replay: Option<Replay>,
+    /// Data passed to RETURN or REVERT, None for bytes that are not constants. None when the path
+    /// ended another way or the range is not constant.
+    pub output: Option<Vec<Option<u8>>>,
+    /// Arithmetic that can wrap on this path, when overflow checks are enabled
+    pub overflows: Vec<Overflow>,
+    /// Storage accesses, calls and self-destructs along the path, in order
+    pub effects: Vec<Effect>,
+    /// Selector the calldata was fixed to, when exploring a single function
+    pub selector: Option<[u8; 4]>,
+}
+
+/// Explores every path through the code, forking the context at each JUMPI
+pub struct Explorer {
+    pub handlers: [OpcodeHandler; 256],
+    /// Print branch verdicts and exceptional halts as they are found
+    pub verbose: bool,
+    /// Every path explored so far
+    pub results: Vec<PathResult>,
+    /// Tree of the forks made so far, filled in when set
+    pub tree: Option<DotTree>,
+    /// Decides the path conditions, holding the condition of the path being explored
+    pub solver: Box<dyn Solver>,
+    /// Replays the model of every satisfiable path on this interpreter when set
+    pub replay: Option<ConcreteEvm>,
+    /// Check the ADD, SUB and MUL of every successful path for wrapping when set. Needs a solver
+    /// that decides 256-bit arithmetic.
+    pub overflow: bool,
+    /// Selector the calldata is fixed to while exploring a single function
+    pub selector: Option<[u8; 4]>,
+}
+
+impl Explorer {
+    pub fn new(handlers: [OpcodeHandler; 256]) -> Self {
+        Self {
+            handlers,
+            verbose: false,
+            results: Vec::new(),
+            tree: None,
+            solver: Box::new(DlSolver::default()),
+            replay: None,
+            overflow: false,
+            selector: None,
+        }
+    }
+
+    /// Explore only `function`. The selector the dispatcher reads is fixed, and so is its check
+    /// that calldata holds a selector, so the dispatcher goes to the function without forking.
+    /// `assumptions`, such as the ranges of typed arguments, hold on every path.
+    pub fn run_function(
+        &mut self,
+        context: &mut EvmContext,
+        function: &Function,
+        assumptions: &[Term],
+    ) -> u64 {
+        self.selector = Some(function.selector);
+        self.solver.push();
+        for assumption in assumptions {
+            self.solver.assert_branch(&Branch {
+                pc: function.entry,
+                taken: true,
+                condition: assumption.clone(),
+            });
+        }
+        let counter = self.run(context);
+        self.solver.pop();
+        self.selector = None;
+        counter
+    }
+
+    pub fn run(&mut self, context: &mut EvmContext) -> u64 {
+        // Interpret the runtime bytecode
+        while context.pc < context.code.len() {
+            // Exceptional halts end the path, as on mainnet
+            if context.halt.is_some() {
+                self.finish_halt(context);
+                return context.counter;
+            }
+
+            let opcode = context.code[context.pc];
+
+            // No loops
+            if search_path(&context.path, &context.pc) {
+                return context.counter;
+            }
+
+            context.path.push(context.pc);
+
+            // Extract the handler for this opcode
+            let handler = self.handlers[opcode as usize].clone();
+
+            // Stack errors halt like the other exceptional halts, at the top of the loop
+            let depth = context.sym_stack.free_top;
+            let (in_args, out_args) = (handler.in_args as usize, handler.out_args as usize);
+            if depth < in_args {
+                context.halt = Some(HaltReason::StackUnderflow);
+                continue;
+            }
+            if depth - in_args + out_args > STACK_LIMIT {
+                context.halt = Some(HaltReason::StackOverflow);
+                continue;
+            }
+
+            // We dont create symbolic values for push, dup, swap.
+            if (PUSH0..=SWAP16).contains(&opcode) {
+                (handler.handler)(&handler, context, &mut []);
+                continue;
+            }
+
+            // The other opcodes have either 0 or 1 output values
+            let mut sym_args: Vec<Term> = Vec::new();
+            let mut sym_op: Vec<Term> = Vec::new();
+
+            for i in 0..handler.in_args {
+                sym_args.push(
+                    context.sym_stack.values[context.sym_stack.free_top - (i as usize) - 1].clone(),
+                );
+            }
+            for _ in 0..handler.out_args {
+                let tmp = Term {
+                    sym_val: SymVal {
+                        value: opcode as u64,
+                        kind: Kind::Symbolic,
+                    },
+                    args: sym_args.clone(),
+                };
+                sym_op.push(tmp);
+            }
+
+            // With the function fixed, so is the selector the dispatcher compares
+            if let Some(selector) = self.selector {
+                let value = match sym_op.first() {
+                    Some(term) if is_selector(term) => Some(u32::from_be_bytes(selector) as u64),
+                    Some(term) if is_short_calldata_check(term) => Some(0),
+                    _ => None,
+                };
+                if let Some(value) = value {
+                    sym_op[0] = Term::constant(value);
+                }
+            }
+
+            // Operations over known values have a known result, e.g. a lock read from transient
+            // storage at the start of a transaction, or offsets computed by an ABI decoder
+            if let Some(value) = fold_concrete(opcode, &sym_args) {
+                sym_op[0] = Term::constant(value);
+            }
+
+            let prev_pc = context.pc;
+            (handler.handler)(&handler, context, &mut sym_op);
+
+            if self.overflow && sym_op.first().is_some_and(is_checked) {
+                context.arithmetic.push((prev_pc, sym_op[0].clone()));
+            }
+            if is_effect(opcode) {
+                context.effects.push(Effect {
+                    pc: prev_pc,
+                    opcode,
+                    args: sym_args.clone(),
+                    result: sym_op.first().cloned(),
+                });
+            }
+
+            let known_condition = match opcode {
+                JUMPI => evaluate_term(&sym_args[1]),
+                _ => None,
+            };
+            if let Some(condition) = known_condition {
+                // Only one direction is possible, keep going down it without forking
+                let taken = !condition.is_zero();
+                if !taken {
+                    context.halt = None;
+                    context.pc = prev_pc + 1;
+                }
+                context.branches.push(Branch {
+                    pc: prev_pc,
+                    taken,
+                    condition: sym_args[1].clone(),
+                });
+            } else if opcode == JUMPI {
+                let taken = Branch {
+                    pc: prev_pc,
+                    taken: true,
+                    condition: sym_args[1].clone(),
+                };
+                let not_taken = Branch {
+                    taken: false,
+                    ..taken.clone()
+                };
+
+                // at a branching point, check the taken side in its own scope
+                self.solver.push();
+                self.solver.assert_branch(&taken);
+                let result = self.solver.check();
+                if self.verbose {
+                    println!("RESULT: {:?}", result);
+                }
+
+                let mut new_context = context.clone();
+                new_context
+                    .constraints
+                    .extend(branch_to_expressions(&taken));
+                if let Some(tree) = &mut self.tree {
+                    let node = tree.fork(context.tree_parent, prev_pc, &sym_args[1]);
+                    new_context.tree_parent = Some((node, true));
+                    context.tree_parent = Some((node, false));
+                }
+                new_context.branches.push(taken);
+                context.halt = None;
+                context.counter = self.run(&mut new_context);
+                self.solver.pop();
+
+                context.pc = prev_pc + 1;
+                self.solver.assert_branch(&not_taken);
+                context
+                    .constraints
+                    .extend(branch_to_expressions(&not_taken));
+                context.branches.push(not_taken);
+            } else if is_terminating(opcode) {
+                let output = match opcode {
+                    RETURN | REVERT => context.memory.bytes(&sym_args[0], &sym_args[1]),
+                    _ => None,
+                };
+                self.finish(context, opcode, prev_pc, output);
+                return context.counter;
+            }
+        }
+
+        // A jump past the end of the code leaves the loop with the halt still to record
+        if context.halt.is_some() {
+            self.finish_halt(context);
+            return context.counter;
+        }
+
+        // Running off the end of the code is an implicit STOP
+        self.finish(context, STOP, context.pc, None);
+        context.counter
+    }
+
+    /// Record a path that halted exceptionally, at the instruction that halted
+    fn finish_halt(&mut self, context: &EvmContext) {
+        if self.verbose {
+            if let Some(halt) = &context.halt {
+                println!("HALT: {:?}", halt);
+            }
+        }
+        let pc = context.path.last().copied().unwrap_or(context.pc);
+        self.finish(context, context.code[pc], pc, None);
+    }
+
+    /// Record the result of a path that has ended
+    fn finish(
+        &mut self,
+        context: &EvmContext,
+        halt_opcode: u8,
+        halt_pc: usize,
+        output: Option<Vec<Option<u8>>>,
+    ) {
+        let result = self.solver.check();
+        let model = match result {
+            DLResult::Sat => self.solver.model(),
+            _ => None,
+        };
+        if let Some(tree) = &mut self.tree {
+            tree.leaf(context.tree_parent, halt_pc, halt_opcode, result);
+        }
+        let mut path_result = PathResult {
+            branches: context.branches.clone(),
+            path: context.path.clone(),
+            constraints: context.constraints.clone(),
+            result,
+            model,
+            halt_opcode,
+            halt_pc,
+            halt: context.halt.clone(),
+            replay: None,
+            output,
+            overflows: Vec::new(),
+            effects: context.effects.clone(),
+            selector: self.selector,
+        };
+
+        // Wrapping only matters on paths that go through with it. Checked arithmetic reverts
+        // when it wraps, so its successful paths exclude the wrap.
+        if result == DLResult::Sat && classify(&path_result).is_none() {
+            path_result.overflows = self.overflows(context);
+        }
+
+        // Check that the model really drives execution down this path
+        if let (Some(evm), Some(model)) = (&self.replay, &path_result.model) {
+            let outcome = replay(evm, &context.code, &path_result, model);
+            if self.verbose {
+                println!("REPLAY: {:?}", outcome);
+            }
+            path_result.replay = Some(outcome);
+        }
+        self.results.push(path_result);
+    }
+
+    /// Arithmetic on the path that can wrap under the path condition
+    fn overflows(&mut self, context: &EvmContext) -> Vec<Overflow> {
+        let mut overflows = Vec::new();
+        for (pc, term) in &context.arithmetic {
+            let condition = match wrap_condition(term) {
+                Some(condition) => condition,
+                None => continue,
+            };
+            self.solver.push();
+            self.solver.assert_branch(&Branch {
+                pc: *pc,
+                taken: true,
+                condition: condition.clone(),
+            });
+            if self.solver.check() == DLResult::Sat {
+                overflows.push(Overflow {
+                    pc: *pc,
+                    opcode: term.sym_val.value as u8,
+                    condition,
+                    model: self.solver.model(),
+                });
+            }
+            self.solver.pop();
+        }
+        overflows
+    }
+}
+
+// Evaluate pure operations whose operands are all known, when the result fits a concrete term.
+// Operands can be words wider than a concrete term, which are evaluated in full.
+fn fold_concrete(opcode: u8, args: &[Term]) -> Option<u64> {
+    if args.is_empty() {
+        return None;
+    }
+    let values = args
+        .iter()
+        .map(evaluate_term)
+        .collect::<Option<Vec<U256>>>()?;
+    u64::try_from(arithmetic(opcode, &values)?).ok()
+}
+
+fn search_path(_path: &[usize], _pc: &usize) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardfork::Hardfork;
+    use crate::sym_stack::is_concrete;
+
+    fn explore(code: &str) -> Vec<PathResult> {
+        let mut explorer = Explorer::new(sym_handlers(Hardfork::default()));
+        explorer.run(&mut EvmContext::new(hex::decode(code).unwrap()));
+        explorer.results
+    }
+
+    // Directions taken at the first JUMPI of every path
+    fn directions(results: &[PathResult]) -> Vec<bool> {
+        results
+            .iter()
+            .map(|result| result.branches[0].taken)
+            .collect()
+    }
+
+    #[test]
+    fn mapping_reads_of_other_keys_fork() {
+        // m[caller] = 1; if (m[calldataload(0)] == 1)
+        let results = explore("336000526001604060002055600035600052604060002054600114601f57005b00");
+        assert_eq!(directions(&results), vec![true, false]);
+    }
+
+    #[test]
+    fn mapping_reads_of_the_written_key_see_the_write() {
+        // m[caller] = 1; if (m[caller] == 1)
+        let results = explore("33600052600160406000205533600052604060002054600114601d57005b00");
+        assert_eq!(results.len(), 1);
+        assert!(results[0].branches[0].taken);
+        assert!(is_concrete(&results[0].branches[0].condition));
+    }
+
+    #[test]
+    fn wide_pushes_fold_to_their_full_value() {
+        // if (iszero(2**72)) jump
+        let results = explore("690100000000000000000015601057005b00");
+        assert_eq!(results.len(), 1);
+        assert!(!results[0].branches[0].taken);
+        assert_eq!(results[0].halt_pc, 0xf);
+    }
+
+    #[test]
+    fn stack_errors_halt_the_path() {
+        let results = explore("01");
+        assert_eq!(results[0].halt, Some(HaltReason::StackUnderflow));
+        assert_eq!((results[0].halt_opcode, results[0].halt_pc), (ADD, 0));
+
+        let results = explore(&"5f".repeat(1025));
+        assert_eq!(results[0].halt, Some(HaltReason::StackOverflow));
+        assert_eq!(results[0].halt_pc, 1024);
+    }
+
+    #[test]
+    fn jumps_past_the_end_halt_at_the_jump() {
+        let results = explore("61ffff56");
+        assert_eq!(results[0].halt, Some(HaltReason::InvalidJump(0xffff)));
+        assert_eq!((results[0].halt_opcode, results[0].halt_pc), (JUMP, 3));
+
+        let results = explore("600161ffff57");
+        assert_eq!(results[0].halt, Some(HaltReason::InvalidJump(0xffff)));
+        assert_eq!((results[0].halt_opcode, results[0].halt_pc), (JUMPI, 5));
+    }
+}
@@ -0,0 +1,178 @@
+use std::fmt;
+
+use crate::explorer::PathResult;
+use crate::handlers::HaltReason;
+use crate::opcodes::*;
+
+/// Selector of Solidity's `Panic(uint256)`
+pub const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+/// Selector of Solidity's `Error(string)`
+pub const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// How a failing path failed, decoded from its halting opcode and revert data
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathFailure {
+    /// `Panic(uint256)` with its code, raised by assert and checked arithmetic
+    Panic(u64),
+    /// `Error(string)`, raised by require and revert with a reason
+    Error,
+    /// Revert with a custom error selector
+    CustomError([u8; 4]),
+    /// Revert with no data, or data we could not decode
+    Revert,
+    /// The INVALID opcode, which is how assert failed before Solidity 0.8
+    Invalid,
+    /// Exceptional halt such as a jump to a bad destination
+    Halt(HaltReason),
+}
+
+impl PathFailure {
+    /// Whether this is a failed assertion: Panic(0x01) or the INVALID opcode
+    pub fn is_assertion(&self) -> bool {
+        matches!(self, PathFailure::Panic(0x01) | PathFailure::Invalid)
+    }
+}
+
+/// What a Solidity panic code means
+pub fn panic_description(code: u64) -> &'static str {
+    match code {
+        0x00 => "generic compiler panic",
+        0x01 => "assertion failed",
+        0x11 => "arithmetic overflow or underflow",
+        0x12 => "division or modulo by zero",
+        0x21 => "conversion to an invalid enum value",
+        0x22 => "incorrectly encoded storage byte array",
+        0x31 => "pop on an empty array",
+        0x32 => "array index out of bounds",
+        0x41 => "too much memory allocated",
+        0x51 => "call to a zero-initialized function variable",
+        _ => "unknown panic code",
+    }
+}
+
+impl fmt::Display for PathFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathFailure::Panic(code) => {
+                write!(f, "Panic({:#04x}): {}", code, panic_description(*code))
+            }
+            PathFailure::Error => write!(f, "Error(string)"),
+            PathFailure::CustomError(selector) => {
+                write!(f, "custom error 0x{}", hex::encode(selector))
+            }
+            PathFailure::Revert => write!(f, "REVERT"),
+            PathFailure::Invalid => write!(f, "INVALID"),
+            PathFailure::Halt(halt) => write!(f, "exceptional halt {:?}", halt),
+        }
+    }
+}
+
+/// Classify how a path failed, None when it ended successfully
+pub fn classify(result: &PathResult) -> Option<PathFailure> {
+    if let Some(halt) = &result.halt {
+        return Some(PathFailure::Halt(halt.clone()));
+    }
+    match result.halt_opcode {
+        INVALID => Some(PathFailure::Invalid),
+        REVERT => Some(decode_revert(result.output.as_deref().unwrap_or(&[]))),
+        _ => None,
+    }
+}
+
+/// Decode revert data whose bytes may be unknown
+pub fn decode_revert(data: &[Option<u8>]) -> PathFailure {
+    let selector: Option<[u8; 4]> = data
+        .get(..4)
+        .and_then(|bytes| bytes.iter().copied().collect::<Option<Vec<u8>>>())
+        .and_then(|bytes| bytes.try_into().ok());
+    let selector = match selector {
+        Some(selector) => selector,
+        None => return PathFailure::Revert,
+    };
+
+    match selector {
+        PANIC_SELECTOR => {
+            // The code is a single ABI word, we only need its low bytes
+            let word: Option<Vec<u8>> = data
+                .get(4..36)
+                .and_then(|bytes| bytes.iter().copied().collect());
+            match word {
+                Some(word) if word[..24].iter().all(|&byte| byte == 0) => {
+                    let code = word[24..]
+                        .iter()
+                        .fold(0u64, |code, &byte| code << 8 | byte as u64);
+                    PathFailure::Panic(code)
+                }
+                _ => PathFailure::Revert,
+            }
+        }
+        ERROR_SELECTOR => PathFailure::Error,
+        _ => PathFailure::CustomError(selector),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn known(bytes: &[u8]) -> Vec<Option<u8>> {
+        bytes.iter().copied().map(Some).collect()
+    }
+
+    fn panic_data(code: u8) -> Vec<u8> {
+        let mut data = PANIC_SELECTOR.to_vec();
+        data.extend([0; 31]);
+        data.push(code);
+        data
+    }
+
+    #[test]
+    fn revert_data_decodes_by_selector() {
+        assert_eq!(
+            decode_revert(&known(&panic_data(0x11))),
+            PathFailure::Panic(0x11)
+        );
+        assert_eq!(decode_revert(&known(&ERROR_SELECTOR)), PathFailure::Error);
+        assert_eq!(
+            decode_revert(&known(&[1, 2, 3, 4, 5])),
+            PathFailure::CustomError([1, 2, 3, 4])
+        );
+        assert_eq!(decode_revert(&[]), PathFailure::Revert);
+    }
+
+    #[test]
+    fn unknown_or_malformed_data_is_a_plain_revert() {
+        let mut selector = known(&ERROR_SELECTOR);
+        selector[2] = None;
+        assert_eq!(decode_revert(&selector), PathFailure::Revert);
+
+        // A panic code wider than eight bytes, and one cut short
+        let mut wide = panic_data(0x01);
+        wide[4] = 1;
+        assert_eq!(decode_revert(&known(&wide)), PathFailure::Revert);
+        assert_eq!(
+            decode_revert(&known(&panic_data(0x01)[..20])),
+            PathFailure::Revert
+        );
+
+        let mut unknown_code = known(&panic_data(0x01));
+        unknown_code[35] = None;
+        assert_eq!(decode_revert(&unknown_code), PathFailure::Revert);
+    }
+
+    #[test]
+    fn assertions_are_panic_one_or_invalid() {
+        assert!(PathFailure::Panic(0x01).is_assertion());
+        assert!(PathFailure::Invalid.is_assertion());
+        assert!(!PathFailure::Panic(0x11).is_assertion());
+        assert!(!PathFailure::Error.is_assertion());
+        assert_eq!(
+            PathFailure::Panic(0x12).to_string(),
+            "Panic(0x12): division or modulo by zero"
+        );
+        assert_eq!(
+            PathFailure::CustomError([0xde, 0xad, 0xbe, 0xef]).to_string(),
+            "custom error 0xdeadbeef"
+        );
+    }
+}
@@ -0,0 +1,492 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::access::{any_sender, origin_comparisons};
+use crate::explorer::PathResult;
+use crate::failures::{classify, PathFailure};
+use crate::handlers::Branch;
+use crate::opcodes::*;
+use crate::overflow::describe;
+use crate::reentrancy::reentrancies;
+use crate::replay::Replay;
+use crate::solve::{DLResult, Model};
+use crate::sym_stack::{contains, is_concrete, mentions, Term};
+use crate::transaction::Transaction;
+
+/// Kinds of issues the engine reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rule {
+    /// A REVERT that some satisfiable path reaches
+    ReachableRevert,
+    /// A failed assertion, Panic(0x01) or INVALID, that some satisfiable path reaches
+    AssertionFailure,
+    /// Any other Solidity panic that some satisfiable path reaches
+    Panic,
+    /// An exceptional halt, such as a bad jump, that some satisfiable path reaches
+    ExceptionalHalt,
+    /// A SELFDESTRUCT that any sender can reach
+    UnprotectedSelfdestruct,
+    /// A DELEGATECALL that any sender can reach
+    UnprotectedDelegatecall,
+    /// A DELEGATECALL that any sender can reach with a target taken from calldata
+    ControlledDelegatecall,
+    /// An external call that can re-enter before a slot it depends on is updated
+    Reentrancy,
+    /// A JUMPI that authenticates with tx.origin
+    TxOriginAuth,
+    /// A call whose success flag no JUMPI looks at
+    UncheckedCall,
+    /// An SSTORE to a fixed slot that any sender can reach
+    UnprotectedStorageWrite,
+    /// An ADD, SUB or MUL that can wrap around on a path that completes without failing
+    ArithmeticOverflow,
+    /// A JUMPI direction that no satisfiable path takes
+    DeadBranch,
+    /// A model that does not drive concrete execution down the path it was found for
+    ImpreciseEncoding,
+}
+
+/// How serious a finding is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Rule {
+    /// Stable identifier of the rule
+    pub fn id(self) -> &'static str {
+        match self {
+            Rule::ReachableRevert => "reachable-revert",
+            Rule::AssertionFailure => "assertion-failure",
+            Rule::Panic => "panic",
+            Rule::ExceptionalHalt => "exceptional-halt",
+            Rule::UnprotectedSelfdestruct => "unprotected-selfdestruct",
+            Rule::UnprotectedDelegatecall => "unprotected-delegatecall",
+            Rule::ControlledDelegatecall => "controlled-delegatecall",
+            Rule::Reentrancy => "reentrancy",
+            Rule::TxOriginAuth => "tx-origin-auth",
+            Rule::UncheckedCall => "unchecked-call",
+            Rule::UnprotectedStorageWrite => "unprotected-sstore",
+            Rule::ArithmeticOverflow => "arithmetic-overflow",
+            Rule::DeadBranch => "dead-branch",
+            Rule::ImpreciseEncoding => "imprecise-encoding",
+        }
+    }
+
+    /// One line description of what the rule detects
+    pub fn description(self) -> &'static str {
+        match self {
+            Rule::ReachableRevert => "A REVERT is reachable",
+            Rule::AssertionFailure => "An assertion can fail",
+            Rule::Panic => "A Solidity panic is reachable",
+            Rule::ExceptionalHalt => "An exceptional halt is reachable",
+            Rule::UnprotectedSelfdestruct => "Any sender can destroy the contract",
+            Rule::UnprotectedDelegatecall => "Any sender can reach a DELEGATECALL",
+            Rule::ControlledDelegatecall => {
+                "Any sender can DELEGATECALL into an address they choose"
+            }
+            Rule::Reentrancy => "A slot read before an external call is written after it",
+            Rule::TxOriginAuth => "Authentication relies on tx.origin",
+            Rule::UncheckedCall => "The success of an external call is never checked",
+            Rule::UnprotectedStorageWrite => "Any sender can write a fixed storage slot",
+            Rule::ArithmeticOverflow => "Unchecked arithmetic can wrap around",
+            Rule::DeadBranch => "One direction of a JUMPI is never feasible",
+            Rule::ImpreciseEncoding => {
+                "A solver model does not reproduce its path when replayed concretely"
+            }
+        }
+    }
+
+    pub fn level(self) -> Level {
+        match self {
+            Rule::ReachableRevert => Level::Note,
+            Rule::AssertionFailure => Level::Error,
+            Rule::Panic => Level::Warning,
+            Rule::ExceptionalHalt => Level::Error,
+            Rule::UnprotectedSelfdestruct => Level::Error,
+            Rule::UnprotectedDelegatecall => Level::Warning,
+            Rule::ControlledDelegatecall => Level::Error,
+            Rule::Reentrancy => Level::Error,
+            Rule::TxOriginAuth => Level::Warning,
+            Rule::UncheckedCall => Level::Warning,
+            Rule::UnprotectedStorageWrite => Level::Warning,
+            Rule::ArithmeticOverflow => Level::Warning,
+            Rule::DeadBranch => Level::Warning,
+            Rule::ImpreciseEncoding => Level::Warning,
+        }
+    }
+}
+
+/// An issue found at a specific pc
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub rule: Rule,
+    /// Pc of the offending instruction
+    pub pc: usize,
+    pub message: String,
+    /// Assignment that drives execution to the finding, when there is one
+    pub witness: Option<Model>,
+    /// Transaction built from the witness
+    pub transaction: Option<Transaction>,
+    /// JUMPI decisions leading to the finding
+    pub condition: Vec<Branch>,
+    /// Other instructions involved, with the part they play
+    pub related: Vec<(usize, String)>,
+}
+
+impl Finding {
+    /// A finding on `result`, with its model as the witness
+    fn on_path(rule: Rule, pc: usize, message: String, result: &PathResult) -> Self {
+        Self {
+            rule,
+            pc,
+            message,
+            witness: result.model.clone(),
+            transaction: result
+                .model
+                .as_ref()
+                .map(|model| Transaction::on_path(result, model)),
+            condition: result.branches.clone(),
+            related: Vec::new(),
+        }
+    }
+}
+
+/// Derive findings from the explored paths, one per rule and pc
+pub fn findings(results: &[PathResult]) -> Vec<Finding> {
+    let mut found: BTreeMap<(Rule, usize), Finding> = BTreeMap::new();
+
+    for result in results.iter().filter(|r| r.result == DLResult::Sat) {
+        let failure = match classify(result) {
+            Some(failure) => failure,
+            None => continue,
+        };
+        let rule = match &failure {
+            _ if failure.is_assertion() => Rule::AssertionFailure,
+            PathFailure::Panic(_) => Rule::Panic,
+            PathFailure::Halt(_) => Rule::ExceptionalHalt,
+            _ => Rule::ReachableRevert,
+        };
+        let message = format!("Path ends with {}", failure);
+        let finding = Finding::on_path(rule, result.halt_pc, message, result);
+        found.entry((finding.rule, finding.pc)).or_insert(finding);
+    }
+
+    // Paths that go through without authenticating who sent the transaction
+    for result in results.iter().filter(|r| r.result == DLResult::Sat) {
+        if !any_sender(result) || classify(result).is_some() {
+            continue;
+        }
+        for effect in &result.effects {
+            let (rule, message) = match effect.opcode {
+                SELFDESTRUCT => (
+                    Rule::UnprotectedSelfdestruct,
+                    "Any sender can reach this SELFDESTRUCT".to_string(),
+                ),
+                DELEGATECALL if mentions(&effect.args[1], CALLDATALOAD) => (
+                    Rule::ControlledDelegatecall,
+                    format!(
+                        "Any sender can reach this DELEGATECALL, its target {} comes from calldata",
+                        effect.args[1]
+                    ),
+                ),
+                DELEGATECALL => (
+                    Rule::UnprotectedDelegatecall,
+                    "Any sender can reach this DELEGATECALL".to_string(),
+                ),
+                // Writes to computed slots, like mapping entries, are per-user state
+                SSTORE if is_concrete(&effect.args[0]) => {
+                    let value = &effect.args[1];
+                    let chosen = [CALLER, ORIGIN, CALLDATALOAD]
+                        .iter()
+                        .any(|&source| mentions(value, source));
+                    (
+                        Rule::UnprotectedStorageWrite,
+                        format!(
+                            "Any sender can write storage slot {:#x}{}",
+                            effect.args[0].sym_val.value,
+                            if chosen {
+                                " with a value they choose"
+                            } else {
+                                ""
+                            }
+                        ),
+                    )
+                }
+                _ => continue,
+            };
+            found
+                .entry((rule, effect.pc))
+                .or_insert_with(|| Finding::on_path(rule, effect.pc, message, result));
+        }
+    }
+
+    // Paths that go through with an external call between reading and writing a slot
+    for result in results.iter().filter(|r| r.result == DLResult::Sat) {
+        if classify(result).is_some() {
+            continue;
+        }
+        for reentrancy in reentrancies(&result.effects) {
+            let message = format!(
+                "This CALL can re-enter before slot {} read at {:#x} is written at {:#x}",
+                reentrancy.slot, reentrancy.read_pc, reentrancy.write_pc
+            );
+            found
+                .entry((Rule::Reentrancy, reentrancy.call_pc))
+                .or_insert_with(|| Finding {
+                    related: vec![
+                        (reentrancy.read_pc, format!("SLOAD of {}", reentrancy.slot)),
+                        (
+                            reentrancy.write_pc,
+                            format!("SSTORE to {}", reentrancy.slot),
+                        ),
+                    ],
+                    ..Finding::on_path(Rule::Reentrancy, reentrancy.call_pc, message, result)
+                });
+        }
+    }
+
+    // Calls whose success flag never flows into a JUMPI condition before the path ends
+    for result in results.iter().filter(|r| r.result == DLResult::Sat) {
+        if classify(result).is_some() {
+            continue;
+        }
+        for effect in &result.effects {
+            if !matches!(effect.opcode, CALL | CALLCODE | DELEGATECALL | STATICCALL) {
+                continue;
+            }
+            let flag = match &effect.result {
+                Some(flag) => flag,
+                None => continue,
+            };
+            if result
+                .branches
+                .iter()
+                .any(|branch| contains(&branch.condition, flag))
+            {
+                continue;
+            }
+            let message = format!(
+                "The success flag of this {} never reaches a JUMPI",
+                opcode_name(effect.opcode)
+            );
+            found
+                .entry((Rule::UncheckedCall, effect.pc))
+                .or_insert_with(|| {
+                    Finding::on_path(Rule::UncheckedCall, effect.pc, message, result)
+                });
+        }
+    }
+
+    // A contract the owner calls into can pass a tx.origin check on their behalf
+    for result in results.iter().filter(|r| r.result == DLResult::Sat) {
+        for branch in &result.branches {
+            let compared = origin_comparisons(&branch.condition);
+            if compared.is_empty() {
+                continue;
+            }
+            let message = format!(
+                "This JUMPI authenticates by comparing tx.origin with {}",
+                compared.join(", ")
+            );
+            found
+                .entry((Rule::TxOriginAuth, branch.pc))
+                .or_insert_with(|| {
+                    Finding::on_path(Rule::TxOriginAuth, branch.pc, message, result)
+                });
+        }
+    }
+
+    // The witness has to drive execution down the path and make the operation wrap
+    for result in results {
+        for overflow in &result.overflows {
+            let conditions: Vec<&Term> = result
+                .branches
+                .iter()
+                .map(|branch| &branch.condition)
+                .chain([&overflow.condition])
+                .collect();
+            found
+                .entry((Rule::ArithmeticOverflow, overflow.pc))
+                .or_insert_with(|| Finding {
+                    rule: Rule::ArithmeticOverflow,
+                    pc: overflow.pc,
+                    message: describe(overflow.opcode).to_string(),
+                    witness: overflow.model.clone(),
+                    transaction: overflow
+                        .model
+                        .as_ref()
+                        .map(|model| Transaction::new(&conditions, model, result.selector)),
+                    condition: result.branches.clone(),
+                    related: Vec::new(),
+                });
+        }
+    }
+
+    // Replays that went another way point at conditions the solver encoded wrongly
+    for result in results {
+        let (pc, message) = match &result.replay {
+            Some(Replay::Diverged {
+                pc,
+                expected,
+                actual,
+            }) => (
+                *pc,
+                format!(
+                    "Path expects this JUMPI to be {} but replaying its model {}",
+                    direction(*expected),
+                    match actual {
+                        Some(taken) => format!("it was {}", direction(*taken)),
+                        None => "never reaches it".to_string(),
+                    }
+                ),
+            ),
+            Some(Replay::HaltMismatch {
+                expected_pc,
+                actual_pc,
+            }) => (
+                *expected_pc,
+                format!(
+                    "Path ends here but replaying its model ends at {:#x}",
+                    actual_pc
+                ),
+            ),
+            _ => continue,
+        };
+        found
+            .entry((Rule::ImpreciseEncoding, pc))
+            .or_insert_with(|| Finding::on_path(Rule::ImpreciseEncoding, pc, message, result));
+    }
+
+    // A direction is dead when it was explored but only on unsatisfiable paths
+    let mut explored: BTreeSet<(usize, bool)> = BTreeSet::new();
+    let mut feasible: BTreeSet<(usize, bool)> = BTreeSet::new();
+    for result in results {
+        for branch in &result.branches {
+            explored.insert((branch.pc, branch.taken));
+            if result.result == DLResult::Sat {
+                feasible.insert((branch.pc, branch.taken));
+            }
+        }
+    }
+    for (pc, taken) in explored.difference(&feasible) {
+        // Only report directions of branches that are themselves reachable
+        if !feasible.contains(&(*pc, !taken)) {
+            continue;
+        }
+        found.entry((Rule::DeadBranch, *pc)).or_insert(Finding {
+            rule: Rule::DeadBranch,
+            pc: *pc,
+            message: format!(
+                "The {} side of this JUMPI is never feasible",
+                if *taken { "taken" } else { "not-taken" }
+            ),
+            witness: None,
+            transaction: None,
+            condition: Vec::new(),
+            related: Vec::new(),
+        });
+    }
+
+    found.into_values().collect()
+}
+
+fn direction(taken: bool) -> &'static str {
+    match taken {
+        true => "taken",
+        false => "not taken",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitblast::BvSolver;
+    use crate::explorer::Explorer;
+    use crate::handlers::{sym_handlers, EvmContext};
+    use crate::hardfork::Hardfork;
+
+    fn findings_of(code: &str) -> Vec<(Rule, usize)> {
+        let code = hex::decode(code).unwrap();
+        let mut explorer = Explorer::new(sym_handlers(Hardfork::default()));
+        explorer.solver = Box::new(BvSolver::new(256));
+        explorer.overflow = true;
+        explorer.run(&mut EvmContext::new(code));
+        findings(&explorer.results)
+            .iter()
+            .map(|finding| (finding.rule, finding.pc))
+            .collect()
+    }
+
+    #[test]
+    fn calls_whose_result_is_dropped_are_unchecked() {
+        // pop(call(gas(), 0, 0, 0, 0, 0, 0))
+        let found = findings_of("5f5f5f5f5f5f5af15000");
+        assert_eq!(found, vec![(Rule::UncheckedCall, 7)]);
+    }
+
+    #[test]
+    fn checking_one_call_does_not_check_an_identical_one() {
+        // call(gas(), 0, 0, 0, 0, 0, 0); pop; if (call(gas(), 0, 0, 0, 0, 0, 0)) jump
+        let found = findings_of("5f5f5f5f5f5f5af1505f5f5f5f5f5f5af1601557005b00");
+        assert!(found.contains(&(Rule::UncheckedCall, 7)));
+        assert!(!found.contains(&(Rule::UncheckedCall, 16)));
+    }
+
+    #[test]
+    fn failing_paths_are_reported_where_they_end() {
+        assert_eq!(findings_of("fe"), vec![(Rule::AssertionFailure, 0)]);
+        assert_eq!(findings_of("5f5ffd"), vec![(Rule::ReachableRevert, 2)]);
+    }
+
+    #[test]
+    fn arithmetic_that_wraps_is_reported() {
+        // sstore(0, calldataload(0) + 1)
+        let found = findings_of("5f356001015f5500");
+        assert!(found.contains(&(Rule::ArithmeticOverflow, 4)));
+    }
+
+    #[test]
+    fn selfdestruct_behind_an_owner_check_is_protected() {
+        assert_eq!(
+            findings_of("33ff"),
+            vec![(Rule::UnprotectedSelfdestruct, 1)]
+        );
+        // if (caller == sload(0)) selfdestruct(caller)
+        let found = findings_of("5f543314600857005b33ff");
+        assert!(!found
+            .iter()
+            .any(|(rule, _)| *rule == Rule::UnprotectedSelfdestruct));
+    }
+
+    #[test]
+    fn delegatecall_targets_from_calldata_are_controlled() {
+        // delegatecall(gas(), calldataload(0), 0, 0, 0, 0)
+        let found = findings_of("5f5f5f5f5f355af400");
+        assert!(found.contains(&(Rule::ControlledDelegatecall, 7)));
+    }
+
+    #[test]
+    fn writes_to_fixed_slots_by_anyone_are_reported() {
+        assert_eq!(
+            findings_of("60015f5500"),
+            vec![(Rule::UnprotectedStorageWrite, 3)]
+        );
+    }
+
+    #[test]
+    fn writes_after_calls_to_the_sender_can_re_enter() {
+        // sload(0); call(gas(), caller(), 0, 0, 0, 0, 0); sstore(0, 0)
+        let found = findings_of("5f54505f5f5f5f5f335af1505f5f5500");
+        assert!(found.contains(&(Rule::Reentrancy, 10)));
+        assert!(found.contains(&(Rule::UncheckedCall, 10)));
+    }
+
+    #[test]
+    fn origin_checks_are_reported_at_the_jumpi() {
+        // if (origin == sload(0)) jump
+        let found = findings_of("325f5414600857005b00");
+        assert!(found.contains(&(Rule::TxOriginAuth, 6)));
+    }
+}
@@ -1,7 +1,16 @@
-use crate::sym_stack::{EvmSymStack, Expr, Kind, SymVal, Term};
+use ruint::aliases::U256;
+
+use crate::cfg::valid_jumpdests;
+use crate::concrete::evaluate_term;
+use crate::dot::TreeParent;
+use crate::hardfork::Hardfork;
+use crate::memory::SymMemory;
+use crate::opcodes::*;
+use crate::storage::{hash_of, opaque_hash, Lookup, SymStorage};
+use crate::sym_stack::{EvmSymStack, Expr, Term};
 
 /// General purpose EvmContext for execution
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct EvmContext {
     /// The runtime bytecode
     pub code: Vec<u8>,
@@ -15,6 +24,84 @@ pub struct EvmContext {
     pub constraints: Vec<Expr>,
     /// Counter??
     pub counter: u64,
+    /// Valid jump destinations: JUMPDEST opcodes that are not part of PUSH data
+    pub jumpdests: Vec<bool>,
+    /// Set when the path stopped with an exceptional halt
+    pub halt: Option<HaltReason>,
+    /// Persistent storage written on this path
+    pub storage: SymStorage,
+    /// Transient storage written on this path. Every exploration is a single transaction from a
+    /// new context, so it starts out empty, as transient storage does at the start of a transaction.
+    pub transient_storage: SymStorage,
+    /// Memory written on this path
+    pub memory: SymMemory,
+    /// JUMPI decisions taken on this path
+    pub branches: Vec<Branch>,
+    /// ADD, SUB and MUL over symbolic operands executed on this path, with their pcs
+    pub arithmetic: Vec<(usize, Term)>,
+    /// Storage accesses, calls and self-destructs executed on this path, in order
+    pub effects: Vec<Effect>,
+    /// Where this path hangs in the exploration tree, when one is recorded
+    pub tree_parent: TreeParent,
+}
+
+/// Direction taken at a JUMPI
+#[derive(Debug, Clone)]
+pub struct Branch {
+    /// Pc of the JUMPI
+    pub pc: usize,
+    /// Whether the jump was taken
+    pub taken: bool,
+    /// The condition the JUMPI branched on
+    pub condition: Term,
+}
+
+/// An instruction that touches storage or other accounts
+#[derive(Debug, Clone)]
+pub struct Effect {
+    pub pc: usize,
+    pub opcode: u8,
+    /// Stack arguments, top first
+    pub args: Vec<Term>,
+    /// Value pushed, such as the success flag of a call
+    pub result: Option<Term>,
+}
+
+/// Reason a path halted exceptionally
+#[derive(Debug, Clone, PartialEq)]
+pub enum HaltReason {
+    /// Jump to a target that is not a valid JUMPDEST
+    InvalidJump(usize),
+    /// Opcode that is undefined in the selected hardfork
+    InvalidOpcode(u8),
+    /// Opcode needing more stack items than there are
+    StackUnderflow,
+    /// Stack grew past 1024 items
+    StackOverflow,
+    /// RETURNDATACOPY reading past the end of the return data
+    ReturnDataOutOfBounds,
+    /// Ran past the step or memory budget, standing in for running out of gas
+    OutOfGas,
+}
+
+impl EvmContext {
+    /// Context at the start of `code`, before anything has run
+    pub fn new(code: Vec<u8>) -> Self {
+        Self {
+            counter: 1,
+            jumpdests: valid_jumpdests(&code),
+            code,
+            ..Default::default()
+        }
+    }
+
+    /// Whether `target` is a JUMPDEST we are allowed to jump to
+    pub fn is_valid_jump(&self, target: u64) -> bool {
+        usize::try_from(target)
+            .ok()
+            .and_then(|target| self.jumpdests.get(target).copied())
+            .unwrap_or(false)
+    }
 }
 
 /// General purpose handler for an opcode.
@@ -23,12 +110,15 @@ pub struct EvmContext {
 #[derive(Debug, Clone)]
 pub struct OpcodeHandler {
     pub handler: fn(&OpcodeHandler, &mut EvmContext, &mut [Term]),
+    /// What kind of handler this is, so other interpreters can share the table
+    pub handler_type: HandlerType,
     pub in_args: u8,
     pub out_args: u8,
-    bytecode_reads: u8,
+    pub bytecode_reads: u8,
 }
 
 /// Type of handlers for each opcode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum HandlerType {
     Unimplemented,
     Push,
@@ -37,6 +127,16 @@ pub enum HandlerType {
     Jump,
     JumpI,
     Terminating,
+    Invalid,
+    SLoad,
+    SStore,
+    TLoad,
+    TStore,
+    Sha3,
+    MLoad,
+    MStore,
+    /// Opcodes writing memory we do not follow, such as copies and call outputs
+    MemoryWrite,
 }
 
 impl OpcodeHandler {
@@ -50,9 +150,19 @@ impl OpcodeHandler {
             HandlerType::Jump => Self::handle_jump,
             HandlerType::JumpI => Self::handle_jumpi,
             HandlerType::Terminating => Self::handle_terminating,
+            HandlerType::Invalid => Self::handle_invalid,
+            HandlerType::SLoad => Self::handle_sload,
+            HandlerType::SStore => Self::handle_sstore,
+            HandlerType::TLoad => Self::handle_tload,
+            HandlerType::TStore => Self::handle_tstore,
+            HandlerType::Sha3 => Self::handle_sha3,
+            HandlerType::MLoad => Self::handle_mload,
+            HandlerType::MStore => Self::handle_mstore,
+            HandlerType::MemoryWrite => Self::handle_memory_write,
         };
         Self {
             handler,
+            handler_type,
             in_args,
             out_args,
             bytecode_reads,
@@ -81,60 +191,178 @@ impl OpcodeHandler {
         self.handle_base(context, sym_vals)
     }
 
+    fn handle_invalid(&self, context: &mut EvmContext, _: &mut [Term]) {
+        context.halt = Some(HaltReason::InvalidOpcode(context.code[context.pc]));
+    }
+
+    fn handle_sload(&self, context: &mut EvmContext, sym_vals: &mut [Term]) {
+        // Slots we have not written to hold what they held before, the SLOAD term
+        let key = context.sym_stack.sym_top();
+        match context.storage.load(&key) {
+            Lookup::Written(value) => sym_vals[0] = value,
+            Lookup::Unwritten => {}
+            Lookup::Unknown => sym_vals[0] = context.storage.fresh(SLOAD, key),
+        }
+        self.handle_base(context, sym_vals);
+        context.pc += 1;
+    }
+
+    fn handle_sstore(&self, context: &mut EvmContext, sym_vals: &mut [Term]) {
+        let key = context.sym_stack.sym_top();
+        let value = context.sym_stack.values[context.sym_stack.free_top - 2].clone();
+        context.storage.store(key, value);
+        self.handle_base(context, sym_vals);
+        context.pc += 1;
+    }
+
+    fn handle_tload(&self, context: &mut EvmContext, sym_vals: &mut [Term]) {
+        // Transient storage starts out zeroed in every transaction
+        let key = context.sym_stack.sym_top();
+        sym_vals[0] = match context.transient_storage.load(&key) {
+            Lookup::Written(value) => value,
+            Lookup::Unwritten => Term::constant(0),
+            Lookup::Unknown => context.transient_storage.fresh(TLOAD, key),
+        };
+        self.handle_base(context, sym_vals);
+        context.pc += 1;
+    }
+
+    fn handle_tstore(&self, context: &mut EvmContext, sym_vals: &mut [Term]) {
+        let key = context.sym_stack.sym_top();
+        let value = context.sym_stack.values[context.sym_stack.free_top - 2].clone();
+        context.transient_storage.store(key, value);
+        self.handle_base(context, sym_vals);
+        context.pc += 1;
+    }
+
+    fn handle_sha3(&self, context: &mut EvmContext, sym_vals: &mut [Term]) {
+        // Mapping and array slots are hashes, keyed on the data hashed so that slots can be told
+        // apart by their keys
+        let top = context.sym_stack.free_top - 1;
+        let offset = context.sym_stack.values[top].clone();
+        let size = context.sym_stack.values[top - 1].clone();
+        sym_vals[0] = match context.memory.words(&offset, &size) {
+            Some(words) => hash_of(words),
+            None => opaque_hash(offset, size, context.path.len()),
+        };
+        self.handle_base(context, sym_vals);
+        context.pc += 1;
+    }
+
+    fn handle_mload(&self, context: &mut EvmContext, sym_vals: &mut [Term]) {
+        // Words we cannot track keep the fresh MLOAD term
+        let offset = context.sym_stack.sym_top();
+        if let Some(value) = context.memory.load(&offset) {
+            sym_vals[0] = value;
+        }
+        self.handle_base(context, sym_vals);
+        context.pc += 1;
+    }
+
+    fn handle_mstore(&self, context: &mut EvmContext, sym_vals: &mut [Term]) {
+        let offset = context.sym_stack.sym_top();
+        let value = context.sym_stack.values[context.sym_stack.free_top - 2].clone();
+        match context.code[context.pc] {
+            MSTORE8 => context.memory.store8(&offset, value),
+            _ => context.memory.store(&offset, value),
+        }
+        self.handle_base(context, sym_vals);
+        context.pc += 1;
+    }
+
+    fn handle_memory_write(&self, context: &mut EvmContext, sym_vals: &mut [Term]) {
+        // Stack positions of the destination offset and size
+        let (offset, size) = match context.code[context.pc] {
+            EXTCODECOPY => (1, 3),
+            CALL | CALLCODE => (5, 6),
+            DELEGATECALL | STATICCALL => (4, 5),
+            _ => (0, 2),
+        };
+        let top = context.sym_stack.free_top - 1;
+        let offset = context.sym_stack.values[top - offset].clone();
+        let size = context.sym_stack.values[top - size].clone();
+        context.memory.clobber(&offset, &size);
+        // Calls with the same arguments can still succeed or fail apart, so the success flag is
+        // tagged with the pc of the call
+        if matches!(
+            context.code[context.pc],
+            CALL | CALLCODE | DELEGATECALL | STATICCALL
+        ) {
+            sym_vals[0].args.push(Term::constant(context.pc as u64));
+        }
+        self.handle_base(context, sym_vals);
+        context.pc += 1;
+    }
+
     fn handle_dup(&self, context: &mut EvmContext, _: &mut [Term]) {
         context.sym_stack.sym_dup(self.in_args);
         context.pc += 1;
     }
 
     fn handle_swap(&self, context: &mut EvmContext, _: &mut [Term]) {
-        context.sym_stack.sym_swap(self.in_args as usize);
+        // SWAPn touches n + 1 items
+        context.sym_stack.sym_swap(self.in_args as usize - 1);
         context.pc += 1;
     }
 
     fn handle_push(&self, context: &mut EvmContext, _: &mut [Term]) {
-        let mut word: u64 = 0;
-        for i in 0..self.bytecode_reads {
-            word |= (context.code[context.pc + i as usize + 1]) as u64;
-            if i < self.bytecode_reads - 1 {
-                word <<= 8;
-            }
+        // Immediates cut short by the end of the code are padded with zeros
+        let start = context.pc + 1;
+        let mut word = [0u8; 32];
+        for i in 0..self.bytecode_reads as usize {
+            word[32 - self.bytecode_reads as usize + i] =
+                context.code.get(start + i).copied().unwrap_or(0);
         }
 
-        let concrete_var = Term {
-            sym_val: SymVal {
-                value: word,
-                kind: Kind::Concrete,
-            },
-            args: Vec::new(),
-        };
-
-        context.sym_stack.sym_push(concrete_var);
+        context
+            .sym_stack
+            .sym_push(Term::word(U256::from_be_bytes(word)));
         context.pc += self.bytecode_reads as usize + 1;
     }
 
     fn handle_jump(&self, context: &mut EvmContext, sym_vals: &mut [Term]) {
-        let top = context.sym_stack.sym_top();
-        if top.sym_val.kind == Kind::Symbolic {
-            panic!("Symbolic Jump");
-        }
-
+        let target = jump_target(&context.sym_stack.sym_top());
         self.handle_base(context, sym_vals);
-        context.pc = top.sym_val.value as usize;
+        context.pc = target as usize;
+        if !context.is_valid_jump(target) {
+            context.halt = Some(HaltReason::InvalidJump(context.pc));
+        }
     }
 
     fn handle_jumpi(&self, context: &mut EvmContext, sym_vals: &mut [Term]) {
-        let top = context.sym_stack.sym_top();
-        if top.sym_val.kind == Kind::Symbolic {
-            panic!("Symbolic Jump");
+        let target = jump_target(&context.sym_stack.sym_top());
+        self.handle_base(context, sym_vals);
+        context.pc = target as usize;
+        // Only the taken side halts, the caller forks the fallthrough before looking at this
+        if !context.is_valid_jump(target) {
+            context.halt = Some(HaltReason::InvalidJump(context.pc));
         }
+    }
+}
 
-        self.handle_base(context, sym_vals);
-        context.pc = top.sym_val.value as usize;
+// Value of a jump target, targets past 64 bits are as invalid as u64::MAX
+fn jump_target(target: &Term) -> u64 {
+    match evaluate_term(target) {
+        Some(value) => u64::try_from(value).unwrap_or(u64::MAX),
+        None => panic!("Symbolic Jump"),
     }
 }
 
-// Register jumptable of handlers for each opcode
-pub fn sym_handlers() -> [OpcodeHandler; 256] {
+// Register jumptable of handlers for each opcode, for the given hardfork
+pub fn sym_handlers(fork: Hardfork) -> [OpcodeHandler; 256] {
+    let mut handlers = all_handlers();
+
+    // Opcodes that are not defined yet in this hardfork halt like INVALID
+    for (opcode, handler) in handlers.iter_mut().enumerate() {
+        if !fork.is_active(opcode as u8) {
+            *handler = OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0);
+        }
+    }
+    handlers
+}
+
+// Jumptable of handlers for every opcode in the latest hardfork
+fn all_handlers() -> [OpcodeHandler; 256] {
     [
         // 0x00 - 0x0F: Stop and Arithmetic Operations
         OpcodeHandler::new(HandlerType::Terminating, 0, 0, 0), // 0x00 STOP: Halts execution
@@ -149,10 +377,10 @@ pub fn sym_handlers() -> [OpcodeHandler; 256] {
         OpcodeHandler::new(HandlerType::Unimplemented, 3, 1, 0), // 0x09 MULMOD: (a * b) % N
         OpcodeHandler::new(HandlerType::Unimplemented, 2, 1, 0), // 0x0A EXP: a ^ b
         OpcodeHandler::new(HandlerType::Unimplemented, 2, 1, 0), // 0x0B SIGNEXTEND: Extends length of two's complement signed integer
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x0C (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x0D (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x0E (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x0F (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0),       // 0x0C (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0),       // 0x0D (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0),       // 0x0E (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0),       // 0x0F (Invalid)
         // 0x10 - 0x1F: Comparison & Bitwise Logic Operations
         OpcodeHandler::new(HandlerType::Unimplemented, 2, 1, 0), // 0x10 LT: a < b
         OpcodeHandler::new(HandlerType::Unimplemented, 2, 1, 0), // 0x11 GT: a > b
@@ -168,25 +396,25 @@ pub fn sym_handlers() -> [OpcodeHandler; 256] {
         OpcodeHandler::new(HandlerType::Unimplemented, 2, 1, 0), // 0x1B SHL: Left shift
         OpcodeHandler::new(HandlerType::Unimplemented, 2, 1, 0), // 0x1C SHR: Right shift
         OpcodeHandler::new(HandlerType::Unimplemented, 2, 1, 0), // 0x1D SAR: Arithmetic right shift
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x1E (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x1F (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0),       // 0x1E (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0),       // 0x1F (Invalid)
         // 0x20: SHA3
-        OpcodeHandler::new(HandlerType::Unimplemented, 2, 1, 0), // 0x20 SHA3: Computes Keccak-256 hash
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x21 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x22 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x23 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x24 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x25 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x26 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x27 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x28 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x29 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x2A (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x2B (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x2C (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x2D (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x2E (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x2F (Invalid)
+        OpcodeHandler::new(HandlerType::Sha3, 2, 1, 0), // 0x20 SHA3: Computes Keccak-256 hash
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0x21 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0x22 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0x23 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0x24 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0x25 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0x26 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0x27 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0x28 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0x29 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0x2A (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0x2B (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0x2C (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0x2D (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0x2E (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0x2F (Invalid)
         // 0x30 - 0x3F: Environmental Information
         OpcodeHandler::new(HandlerType::Unimplemented, 0, 1, 0), // 0x30 ADDRESS: Get address of currently executing accounthandler
         OpcodeHandler::new(HandlerType::Unimplemented, 1, 1, 0), // 0x31 BALANCE: Get balance of given account
@@ -195,14 +423,14 @@ pub fn sym_handlers() -> [OpcodeHandler; 256] {
         OpcodeHandler::new(HandlerType::Unimplemented, 0, 1, 0), // 0x34 CALLVALUE: Get deposited value by the instruction/transaction
         OpcodeHandler::new(HandlerType::Unimplemented, 1, 1, 0), // 0x35 CALLDATALOAD: Get input data of current environment
         OpcodeHandler::new(HandlerType::Unimplemented, 0, 1, 0), // 0x36 CALLDATASIZE: Get size of input data
-        OpcodeHandler::new(HandlerType::Unimplemented, 3, 0, 0), // 0x37 CALLDATACOPY: Copy input data to memory
+        OpcodeHandler::new(HandlerType::MemoryWrite, 3, 0, 0), // 0x37 CALLDATACOPY: Copy input data to memory
         OpcodeHandler::new(HandlerType::Unimplemented, 0, 1, 0), // 0x38 CODESIZE: Get size of code running in current environment
-        OpcodeHandler::new(HandlerType::Unimplemented, 3, 0, 0), // 0x39 CODECOPY: Copy code running in current environment to memory
+        OpcodeHandler::new(HandlerType::MemoryWrite, 3, 0, 0), // 0x39 CODECOPY: Copy code running in current environment to memory
         OpcodeHandler::new(HandlerType::Unimplemented, 0, 1, 0), // 0x3A GASPRICE: Get price of gas in current environment
         OpcodeHandler::new(HandlerType::Unimplemented, 1, 1, 0), // 0x3B EXTCODESIZE: Get size of an account's code
-        OpcodeHandler::new(HandlerType::Unimplemented, 4, 0, 0), // 0x3C EXTCODECOPY: Copy an account's code to memory
+        OpcodeHandler::new(HandlerType::MemoryWrite, 4, 0, 0), // 0x3C EXTCODECOPY: Copy an account's code to memory
         OpcodeHandler::new(HandlerType::Unimplemented, 0, 1, 0), // 0x3D RETURNDATASIZE: Get size of output data from previous call
-        OpcodeHandler::new(HandlerType::Unimplemented, 3, 0, 0), // 0x3E RETURNDATACOPY: Copy output data from previous call to memory
+        OpcodeHandler::new(HandlerType::MemoryWrite, 3, 0, 0), // 0x3E RETURNDATACOPY: Copy output data from previous call to memory
         OpcodeHandler::new(HandlerType::Unimplemented, 1, 1, 0), // 0x3F EXTCODEHASH: Get hash of an account's code
         // 0x40 - 0x47: Block Information
         OpcodeHandler::new(HandlerType::Unimplemented, 1, 1, 0), // 0x40 BLOCKHASH: Get hash of recent block
@@ -214,30 +442,30 @@ pub fn sym_handlers() -> [OpcodeHandler; 256] {
         OpcodeHandler::new(HandlerType::Unimplemented, 0, 1, 0), // 0x46 CHAINID: Get current chain ID
         OpcodeHandler::new(HandlerType::Unimplemented, 0, 1, 0), // 0x47 SELFBALANCE: Get balance of currently executing account
         OpcodeHandler::new(HandlerType::Unimplemented, 0, 1, 0), // 0x48 BASEFEE: Get base fee
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x49 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x4A (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x4B (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x4C (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x4D (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x4E (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x4F (Invalid)
+        OpcodeHandler::new(HandlerType::Unimplemented, 1, 1, 0), // 0x49 BLOBHASH: Get versioned hash of a blob in the transaction
+        OpcodeHandler::new(HandlerType::Unimplemented, 0, 1, 0), // 0x4A BLOBBASEFEE: Get current block's blob base fee
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0),       // 0x4B (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0),       // 0x4C (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0),       // 0x4D (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0),       // 0x4E (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0),       // 0x4F (Invalid)
         // 0x50 - 0x5F: Stack, Memory, Storage and Flow Operations
         OpcodeHandler::new(HandlerType::Unimplemented, 1, 0, 0), // 0x50 POP: Remove item from stack
-        OpcodeHandler::new(HandlerType::Unimplemented, 1, 1, 0), // 0x51 MLOAD: Load word from memory
-        OpcodeHandler::new(HandlerType::Unimplemented, 2, 0, 0), // 0x52 MSTORE: Save word to memory
-        OpcodeHandler::new(HandlerType::Unimplemented, 2, 0, 0), // 0x53 MSTORE8: Save byte to memory
-        OpcodeHandler::new(HandlerType::Unimplemented, 1, 1, 0), // 0x54 SLOAD: Load word from storage
-        OpcodeHandler::new(HandlerType::Unimplemented, 2, 0, 0), // 0x55 SSTORE: Save word to storage
-        OpcodeHandler::new(HandlerType::Jump, 1, 0, 0),          // 0x56 JUMP: Alter program counter
+        OpcodeHandler::new(HandlerType::MLoad, 1, 1, 0), // 0x51 MLOAD: Load word from memory
+        OpcodeHandler::new(HandlerType::MStore, 2, 0, 0), // 0x52 MSTORE: Save word to memory
+        OpcodeHandler::new(HandlerType::MStore, 2, 0, 0), // 0x53 MSTORE8: Save byte to memory
+        OpcodeHandler::new(HandlerType::SLoad, 1, 1, 0), // 0x54 SLOAD: Load word from storage
+        OpcodeHandler::new(HandlerType::SStore, 2, 0, 0), // 0x55 SSTORE: Save word to storage
+        OpcodeHandler::new(HandlerType::Jump, 1, 0, 0),  // 0x56 JUMP: Alter program counter
         OpcodeHandler::new(HandlerType::JumpI, 2, 0, 0), // 0x57 JUMPI: Conditionally alter program counter
         OpcodeHandler::new(HandlerType::Unimplemented, 0, 1, 0), // 0x58 PC: Get program counter
         OpcodeHandler::new(HandlerType::Unimplemented, 0, 1, 0), // 0x59 MSIZE: Get memory size
         OpcodeHandler::new(HandlerType::Unimplemented, 0, 1, 0), // 0x5A GAS: Get available gas
         OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x5B JUMPDEST: Mark valid jump destination
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x5C (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x5D (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x5E (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0x5F (Invalid)
+        OpcodeHandler::new(HandlerType::TLoad, 1, 1, 0), // 0x5C TLOAD: Load word from transient storage
+        OpcodeHandler::new(HandlerType::TStore, 2, 0, 0), // 0x5D TSTORE: Save word to transient storage
+        OpcodeHandler::new(HandlerType::MemoryWrite, 3, 0, 0), // 0x5E MCOPY: Copy memory areas
+        OpcodeHandler::new(HandlerType::Push, 0, 1, 0),   // 0x5F PUSH0: Place value 0 on stack
         // 0x60 - 0x7F: Push Operations
         OpcodeHandler::new(HandlerType::Push, 0, 1, 1), // 0x60 PUSH1: Place 1-byte item on stack
         OpcodeHandler::new(HandlerType::Push, 0, 1, 2), // 0x61 PUSH2: Place 2-byte item on stack
@@ -314,99 +542,123 @@ pub fn sym_handlers() -> [OpcodeHandler; 256] {
         OpcodeHandler::new(HandlerType::Unimplemented, 5, 0, 0), // 0xA3 LOG3: Append log record with three topics
         OpcodeHandler::new(HandlerType::Unimplemented, 6, 0, 0), // 0xA4 LOG4: Append log record with four topics
         // 0xA5 - 0xEF: Invalid range
-        // Fill with unimplemented handlers
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xA5 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xA6 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xA7 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xA8 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xA9 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xAA (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xAB (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xAC (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xAD (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xAE (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xAF (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xB0 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xB1 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xB2 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xB3 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xB4 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xB5 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xB6 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xB7 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xB8 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xB9 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xBA (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xBB (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xBC (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xBD (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xBE (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xBF (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xC0 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xC1 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xC2 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xC3 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xC4 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xC5 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xC6 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xC7 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xC8 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xC9 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xCA (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xCB (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xCC (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xCD (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xCE (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xCF (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xD0 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xD1 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xD2 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xD3 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xD4 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xD5 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xD6 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xD7 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xD8 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xD9 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xDA (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xDB (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xDC (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xDD (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xDE (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xDF (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xE0 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xE1 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xE2 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xE3 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xE4 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xE5 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xE6 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xE7 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xE8 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xE9 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xEA (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xEB (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xEC (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xED (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xEE (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xEF (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xA5 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xA6 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xA7 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xA8 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xA9 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xAA (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xAB (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xAC (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xAD (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xAE (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xAF (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xB0 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xB1 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xB2 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xB3 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xB4 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xB5 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xB6 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xB7 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xB8 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xB9 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xBA (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xBB (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xBC (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xBD (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xBE (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xBF (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xC0 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xC1 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xC2 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xC3 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xC4 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xC5 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xC6 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xC7 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xC8 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xC9 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xCA (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xCB (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xCC (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xCD (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xCE (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xCF (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xD0 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xD1 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xD2 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xD3 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xD4 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xD5 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xD6 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xD7 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xD8 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xD9 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xDA (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xDB (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xDC (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xDD (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xDE (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xDF (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xE0 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xE1 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xE2 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xE3 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xE4 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xE5 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xE6 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xE7 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xE8 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xE9 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xEA (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xEB (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xEC (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xED (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xEE (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xEF (Invalid)
         // 0xF0 - 0xFF: System operations
         OpcodeHandler::new(HandlerType::Unimplemented, 3, 1, 0), // 0xF0 CREATE: Create new contract
-        OpcodeHandler::new(HandlerType::Unimplemented, 7, 1, 0), // 0xF1 CALL: Message-call into account
-        OpcodeHandler::new(HandlerType::Unimplemented, 7, 1, 0), // 0xF2 CALLCODE: Message-call with alternative account's code
+        OpcodeHandler::new(HandlerType::MemoryWrite, 7, 1, 0), // 0xF1 CALL: Message-call into account
+        OpcodeHandler::new(HandlerType::MemoryWrite, 7, 1, 0), // 0xF2 CALLCODE: Message-call with alternative account's code
         OpcodeHandler::new(HandlerType::Terminating, 2, 0, 0), // 0xF3 RETURN: Halt execution returning output data
-        OpcodeHandler::new(HandlerType::Unimplemented, 6, 1, 0), // 0xF4 DELEGATECALL: Message-call into this account with an alternative account's code
+        OpcodeHandler::new(HandlerType::MemoryWrite, 6, 1, 0), // 0xF4 DELEGATECALL: Message-call into this account with an alternative account's code
         OpcodeHandler::new(HandlerType::Unimplemented, 4, 1, 0), // 0xF5 CREATE2: Create new contract with salt
         // 0xF6-0xF9 Invalid
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xF6 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xF7 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xF8 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xF9 (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 6, 1, 0), // 0xFA STATICCALL: Static message-call into account
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xFB (Invalid)
-        OpcodeHandler::new(HandlerType::Unimplemented, 0, 0, 0), // 0xFC (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xF6 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xF7 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xF8 (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0), // 0xF9 (Invalid)
+        OpcodeHandler::new(HandlerType::MemoryWrite, 6, 1, 0), // 0xFA STATICCALL: Static message-call into account
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0),     // 0xFB (Invalid)
+        OpcodeHandler::new(HandlerType::Invalid, 0, 0, 0),     // 0xFC (Invalid)
         OpcodeHandler::new(HandlerType::Terminating, 2, 0, 0), // 0xFD REVERT: Halt execution reverting state changes
         OpcodeHandler::new(HandlerType::Terminating, 0, 0, 0), // 0xFE INVALID: Invalid instruction
         OpcodeHandler::new(HandlerType::Terminating, 1, 0, 0), // 0xFF SELFDESTRUCT: Halt execution and register account for deletion
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swap_exchanges_the_top_with_the_item_n_below() {
+        let handler = sym_handlers(Hardfork::default())[SWAP1 as usize].clone();
+        let mut context = EvmContext {
+            code: vec![SWAP1],
+            ..Default::default()
+        };
+        for value in [1, 2, 3] {
+            context.sym_stack.sym_push(Term::constant(value));
+        }
+        (handler.handler)(&handler, &mut context, &mut []);
+        let values: Vec<u64> = context
+            .sym_stack
+            .values
+            .iter()
+            .map(|term| term.sym_val.value)
+            .collect();
+        assert_eq!(values, vec![1, 3, 2]);
+    }
+}
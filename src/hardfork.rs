@@ -0,0 +1,147 @@
+use anyhow::{anyhow, Error};
+use std::str::FromStr;
+
+use crate::opcodes::*;
+
+/// Mainnet hardforks, in activation order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Hardfork {
+    Frontier,
+    Homestead,
+    TangerineWhistle,
+    SpuriousDragon,
+    Byzantium,
+    Constantinople,
+    Petersburg,
+    Istanbul,
+    Berlin,
+    London,
+    Paris,
+    Shanghai,
+    Cancun,
+    #[default]
+    Prague,
+}
+
+impl Hardfork {
+    /// The hardfork that introduced `opcode`. Opcodes that have never been defined return None.
+    pub fn introduced(opcode: u8) -> Option<Hardfork> {
+        let fork = match opcode {
+            DELEGATECALL => Hardfork::Homestead,
+            RETURNDATASIZE | RETURNDATACOPY | STATICCALL | REVERT => Hardfork::Byzantium,
+            SHL | SHR | SAR | EXTCODEHASH | CREATE2 => Hardfork::Constantinople,
+            CHAINID | SELFBALANCE => Hardfork::Istanbul,
+            BASEFEE => Hardfork::London,
+            PUSH0 => Hardfork::Shanghai,
+            TLOAD | TSTORE | MCOPY | BLOBHASH | BLOBBASEFEE => Hardfork::Cancun,
+            STOP..=SIGNEXTEND
+            | LT..=SAR
+            | SHA3
+            | ADDRESS..=EXTCODEHASH
+            | BLOCKHASH..=SELFBALANCE
+            | POP..=JUMPDEST
+            | PUSH1..=LOG4
+            | CREATE..=CALLCODE
+            | RETURN
+            | INVALID
+            | SELFDESTRUCT => Hardfork::Frontier,
+            _ => return None,
+        };
+        Some(fork)
+    }
+
+    /// Whether `opcode` is defined in this hardfork
+    pub fn is_active(self, opcode: u8) -> bool {
+        Hardfork::introduced(opcode).is_some_and(|fork| fork <= self)
+    }
+}
+
+impl FromStr for Hardfork {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fork = match s.to_lowercase().replace(['-', '_'], "").as_str() {
+            "frontier" => Hardfork::Frontier,
+            "homestead" => Hardfork::Homestead,
+            "tangerinewhistle" => Hardfork::TangerineWhistle,
+            "spuriousdragon" => Hardfork::SpuriousDragon,
+            "byzantium" => Hardfork::Byzantium,
+            "constantinople" => Hardfork::Constantinople,
+            "petersburg" => Hardfork::Petersburg,
+            "istanbul" => Hardfork::Istanbul,
+            "berlin" => Hardfork::Berlin,
+            "london" => Hardfork::London,
+            "paris" | "merge" => Hardfork::Paris,
+            "shanghai" => Hardfork::Shanghai,
+            "cancun" => Hardfork::Cancun,
+            "prague" => Hardfork::Prague,
+            _ => return Err(anyhow!("Unknown hardfork {}", s)),
+        };
+        Ok(fork)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::{sym_handlers, EvmContext, HaltReason};
+
+    #[test]
+    fn names_parse_loosely() {
+        assert_eq!(
+            "Tangerine-Whistle".parse::<Hardfork>().unwrap(),
+            Hardfork::TangerineWhistle
+        );
+        assert_eq!(
+            "spurious_dragon".parse::<Hardfork>().unwrap(),
+            Hardfork::SpuriousDragon
+        );
+        assert_eq!("merge".parse::<Hardfork>().unwrap(), Hardfork::Paris);
+        assert!("osaka".parse::<Hardfork>().is_err());
+        assert_eq!(Hardfork::default(), Hardfork::Prague);
+    }
+
+    #[test]
+    fn opcodes_are_active_from_their_fork_on() {
+        assert_eq!(Hardfork::introduced(ADD), Some(Hardfork::Frontier));
+        assert_eq!(Hardfork::introduced(SHR), Some(Hardfork::Constantinople));
+        assert_eq!(Hardfork::introduced(0x0c), None);
+        assert_eq!(Hardfork::introduced(0xef), None);
+
+        assert!(!Hardfork::Byzantium.is_active(SHR));
+        assert!(Hardfork::Constantinople.is_active(SHR));
+        assert!(Hardfork::Petersburg.is_active(SHR));
+        assert!(!Hardfork::Paris.is_active(PUSH0));
+        assert!(Hardfork::Shanghai.is_active(PUSH0));
+        assert!(Hardfork::Cancun.is_active(TSTORE));
+        assert!(!Hardfork::Prague.is_active(0x0c));
+    }
+
+    #[test]
+    fn opcodes_stay_active_once_introduced() {
+        for opcode in 0..=255u8 {
+            if let Some(fork) = Hardfork::introduced(opcode) {
+                assert!(fork.is_active(opcode), "{:#x}", opcode);
+                assert!(Hardfork::Prague.is_active(opcode), "{:#x}", opcode);
+            }
+        }
+    }
+
+    #[test]
+    fn handlers_of_later_forks_halt() {
+        let step = |fork: Hardfork| {
+            let handler = sym_handlers(fork)[PUSH0 as usize].clone();
+            let mut context = EvmContext {
+                code: vec![PUSH0],
+                ..Default::default()
+            };
+            (handler.handler)(&handler, &mut context, &mut []);
+            context.halt
+        };
+        assert_eq!(
+            step(Hardfork::Paris),
+            Some(HaltReason::InvalidOpcode(PUSH0))
+        );
+        assert_eq!(step(Hardfork::Shanghai), None);
+    }
+}
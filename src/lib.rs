@@ -0,0 +1,30 @@
+pub mod abi;
+pub mod access;
+pub mod bitblast;
+pub mod cfg;
+pub mod concrete;
+pub mod conformance;
+pub mod coverage;
+pub mod deadcode;
+pub mod dispatcher;
+pub mod dot;
+pub mod explorer;
+pub mod failures;
+pub mod findings;
+pub mod handlers;
+pub mod hardfork;
+pub mod memory;
+pub mod opcodes;
+pub mod overflow;
+pub mod reentrancy;
+pub mod replay;
+pub mod report;
+pub mod sarif;
+pub mod sat;
+pub mod smt_process;
+pub mod smtlib;
+pub mod solve;
+pub mod sourcemap;
+pub mod storage;
+pub mod sym_stack;
+pub mod transaction;
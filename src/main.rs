@@ -1,205 +1,437 @@
-mod handlers;
-mod opcodes;
-mod solve;
-mod sym_stack;
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use std::env;
 use std::fs;
+use std::path::Path;
 
-use crate::handlers::*;
-use crate::opcodes::*;
-use crate::solve::solve;
-use crate::sym_stack::{Constant, Expr, Kind, SymVal, Term, Variable};
+use sym_exec::abi::Abi;
+use sym_exec::bitblast::BvSolver;
+use sym_exec::concrete::{ConcreteEvm, ConcreteInput};
+use sym_exec::conformance;
+use sym_exec::coverage::Coverage;
+use sym_exec::deadcode::DeadCode;
+use sym_exec::dispatcher::{self, Function};
+use sym_exec::dot::DotTree;
+use sym_exec::explorer::{Explorer, PathResult};
+use sym_exec::findings::findings;
+use sym_exec::handlers::*;
+use sym_exec::hardfork::Hardfork;
+use sym_exec::report;
+use sym_exec::sarif::{to_sarif, Artifacts};
+use sym_exec::smt_process::SmtProcessSolver;
+use sym_exec::smtlib::to_smtlib;
+use sym_exec::sourcemap::SourceMap;
 
-fn main() -> Result<()> {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        panic!("Need a runtime binary");
-    }
-
-    // Read in the runtime binary
-    let runtime_string = fs::read_to_string(&args[1])?
-        .trim_start_matches("0x")
-        .trim()
-        .to_string();
-    let runtime = hex::decode(runtime_string)?;
+/// How results are printed
+#[derive(PartialEq)]
+enum Format {
+    /// Branch verdicts as they are found
+    Text,
+    /// One JSON record per explored path
+    Json,
+    /// SARIF 2.1.0 log of the findings
+    Sarif,
+    /// Graphviz DOT rendering of the exploration tree
+    Dot,
+    /// SMT-LIB2 QF_BV script of each path condition
+    Smt2,
+    /// Per block instruction coverage and annotated disassembly
+    Coverage,
+    /// Constant JUMPIs and blocks no feasible path reaches
+    DeadCode,
+}
 
-    // Create the Symbolic Evm Context
-    let mut context = EvmContext {
-        counter: 1,
-        code: runtime,
-        ..Default::default()
-    };
+/// Command line options
+struct Args {
+    /// Path to the hex encoded runtime binary
+    binary: Option<String>,
+    /// Hardfork whose opcode set is used
+    fork: Hardfork,
+    /// Output format
+    format: Format,
+    /// Path of a solc source map for the runtime binary
+    source_map: Option<String>,
+    /// Source files, in the order of the source map file indices
+    sources: Vec<String>,
+    /// Solver backend deciding the path conditions
+    solver: SolverChoice,
+    /// Run the code once on this input instead of exploring it symbolically
+    concrete: Option<ConcreteInput>,
+    /// Replay the model of every satisfiable path concretely
+    replay: bool,
+    /// Check arithmetic on successful paths for wrapping
+    overflow: bool,
+    /// List the functions of the dispatcher instead of exploring
+    list_functions: bool,
+    /// Explore only the function with this selector, or this name or signature from the ABI
+    function: Option<String>,
+    /// Path of the contract ABI JSON
+    abi: Option<String>,
+    /// Explore each function of the dispatcher on its own
+    per_function: bool,
+    /// Run the VMTests fixtures in this file or directory instead of analysing a binary
+    conformance: Option<String>,
+}
 
-    // Create all of the handlers
-    let handlers = sym_handlers();
-    run(&mut context, handlers);
-    Ok(())
+/// Which backend decides path conditions
+enum SolverChoice {
+    /// Bellman-Ford difference logic
+    Dl,
+    /// External SMT-LIB2 solver started from the given command line
+    Smt(String),
+    /// Built-in bit-blasting solver over words of the given width
+    Bv(usize),
 }
 
-fn run(context: &mut EvmContext, handlers: [OpcodeHandler; 256]) -> u64 {
-    // Interpret the runtime bytecode
-    while context.pc < context.code.len() {
-        let opcode = context.code[context.pc];
+fn parse_args() -> Result<Args> {
+    let mut binary = None;
+    let mut fork = Hardfork::default();
+    let mut format = Format::Text;
+    let mut source_map = None;
+    let mut sources = Vec::new();
+    let mut solver = SolverChoice::Dl;
+    let mut concrete: Option<ConcreteInput> = None;
+    let mut replay = false;
+    let mut overflow = false;
+    let mut list_functions = false;
+    let mut function = None;
+    let mut abi = None;
+    let mut per_function = false;
+    let mut conformance = None;
 
-        // No loops
-        if search_path(&context.path, &context.pc) {
-            return context.counter;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--fork" => {
+                fork = args
+                    .next()
+                    .ok_or_else(|| anyhow!("--fork needs a hardfork name"))?
+                    .parse()?
+            }
+            "--format" => {
+                format = match args.next().as_deref() {
+                    Some("text") => Format::Text,
+                    Some("json") => Format::Json,
+                    Some("sarif") => Format::Sarif,
+                    Some("dot") => Format::Dot,
+                    Some("smt2") => Format::Smt2,
+                    Some("coverage") => Format::Coverage,
+                    Some("dead-code") => Format::DeadCode,
+                    _ => bail!(
+                        "--format needs one of text, json, sarif, dot, smt2, coverage, dead-code"
+                    ),
+                }
+            }
+            "--source-map" => {
+                source_map = Some(
+                    args.next()
+                        .ok_or_else(|| anyhow!("--source-map needs a file"))?,
+                )
+            }
+            "--solver" => {
+                let choice = args.next().ok_or_else(|| {
+                    anyhow!("--solver needs one of dl, bv, bv:<width>, smt:<solver command>")
+                })?;
+                solver = match choice.split_once(':') {
+                    _ if choice == "dl" => SolverChoice::Dl,
+                    _ if choice == "bv" => SolverChoice::Bv(256),
+                    Some(("smt", command)) => SolverChoice::Smt(command.to_string()),
+                    Some(("bv", width)) => match width.parse::<usize>() {
+                        Ok(width) if width.is_power_of_two() && (8..=256).contains(&width) => {
+                            SolverChoice::Bv(width)
+                        }
+                        _ => bail!("bv width must be a power of two between 8 and 256"),
+                    },
+                    _ => bail!("--solver needs one of dl, bv, bv:<width>, smt:<solver command>"),
+                }
+            }
+            "--sources" => {
+                sources = args
+                    .next()
+                    .ok_or_else(|| anyhow!("--sources needs a comma separated list of files"))?
+                    .split(',')
+                    .map(|source| source.to_string())
+                    .collect()
+            }
+            "--replay" => replay = true,
+            "--overflow" => overflow = true,
+            "--functions" => list_functions = true,
+            "--per-function" => per_function = true,
+            "--function" => {
+                function = Some(
+                    args.next()
+                        .ok_or_else(|| anyhow!("--function needs a selector or a function name"))?,
+                )
+            }
+            "--abi" => {
+                abi = Some(
+                    args.next()
+                        .ok_or_else(|| anyhow!("--abi needs a JSON file"))?,
+                )
+            }
+            "--conformance" => {
+                conformance =
+                    Some(args.next().ok_or_else(|| {
+                        anyhow!("--conformance needs a fixture file or directory")
+                    })?)
+            }
+            "--concrete" => {
+                let calldata = args
+                    .next()
+                    .ok_or_else(|| anyhow!("--concrete needs hex calldata"))?;
+                concrete.get_or_insert_with(Default::default).calldata =
+                    hex::decode(calldata.trim_start_matches("0x"))?;
+            }
+            "--storage" => {
+                let slots = args
+                    .next()
+                    .ok_or_else(|| anyhow!("--storage needs slot=value pairs"))?;
+                let input = concrete.get_or_insert_with(Default::default);
+                for pair in slots.split(',') {
+                    let (slot, value) = pair
+                        .split_once('=')
+                        .ok_or_else(|| anyhow!("Storage entry {} is not slot=value", pair))?;
+                    input.storage.insert(slot.parse()?, value.parse()?);
+                }
+            }
+            "--caller" => {
+                let caller = args
+                    .next()
+                    .ok_or_else(|| anyhow!("--caller needs an address"))?;
+                concrete.get_or_insert_with(Default::default).env.caller = caller.parse()?;
+            }
+            "--callvalue" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| anyhow!("--callvalue needs a value"))?;
+                concrete.get_or_insert_with(Default::default).env.callvalue = value.parse()?;
+            }
+            _ if binary.is_none() => binary = Some(arg),
+            _ => bail!("Unexpected argument {}", arg),
         }
+    }
 
-        context.path.push(context.pc);
+    if binary.is_none() && conformance.is_none() {
+        bail!("Need a runtime binary");
+    }
+    // DL and narrow bit-vectors do not know where 256-bit words wrap
+    if overflow && matches!(solver, SolverChoice::Dl | SolverChoice::Bv(0..=255)) {
+        bail!("--overflow needs a 256-bit solver, --solver bv or smt:<solver command>");
+    }
+    // Narrow bit-vectors truncate constants, so they can prove live code infeasible
+    if matches!(format, Format::Coverage | Format::DeadCode)
+        && matches!(solver, SolverChoice::Bv(0..=255))
+    {
+        bail!("Coverage and dead code reports need a 256-bit solver, not a narrow bv:<width>");
+    }
 
-        // Extract the handler for this opcode
-        let handler = &handlers[opcode as usize];
+    Ok(Args {
+        binary,
+        fork,
+        format,
+        source_map,
+        sources,
+        solver,
+        concrete,
+        replay,
+        overflow,
+        list_functions,
+        function,
+        abi,
+        per_function,
+        conformance,
+    })
+}
 
-        // We dont create symbolic values for push, dup, swap.
-        if (PUSH1..=SWAP16).contains(&opcode) {
-            (handler.handler)(handler, context, &mut []);
-            continue;
+fn print_findings(results: &[PathResult], abi: Option<&Abi>) {
+    for finding in findings(results) {
+        println!(
+            "FINDING: {} at {:#x}: {}",
+            finding.rule.id(),
+            finding.pc,
+            finding.message
+        );
+        if let Some(transaction) = &finding.transaction {
+            let mut transaction = transaction.clone();
+            if let Some(abi) = abi {
+                transaction.complete(abi);
+            }
+            println!("    calldata: 0x{}", hex::encode(&transaction.calldata));
+            if let Some(call) = abi.and_then(|abi| abi.decode_call(&transaction.calldata)) {
+                println!("    call: {}", call);
+            }
+            for command in transaction.commands() {
+                println!("    {}", command);
+            }
         }
+    }
+}
 
-        // The other opcodes have either 0 or 1 output values
-        let mut sym_args: Vec<Term> = Vec::new();
-        let mut sym_op: Vec<Term> = Vec::new();
+// Selector of a function given as hex, or by name or signature from the ABI
+fn parse_selector(function: &str, abi: Option<&Abi>) -> Result<[u8; 4]> {
+    if let Some(hex) = function.strip_prefix("0x") {
+        return hex::decode(hex)?
+            .try_into()
+            .map_err(|_| anyhow!("Selector {} is not 4 bytes", function));
+    }
+    abi.and_then(|abi| abi.find(function))
+        .map(|function| function.selector())
+        .ok_or_else(|| anyhow!("No single function {} in the ABI", function))
+}
 
-        for i in 0..handler.in_args {
-            sym_args.push(
-                context.sym_stack.values[context.sym_stack.free_top - (i as usize) - 1].clone(),
-            );
-        }
-        for _ in 0..handler.out_args {
-            let tmp = Term {
-                sym_val: SymVal {
-                    value: opcode as u64,
-                    kind: Kind::Symbolic,
-                },
-                args: sym_args.clone(),
-            };
-            sym_op.push(tmp);
+fn main() -> Result<()> {
+    let args = parse_args()?;
+
+    // Create all of the handlers
+    let handlers = sym_handlers(args.fork);
+
+    if let Some(path) = &args.conformance {
+        let fixtures = conformance::load(Path::new(path))?;
+        let report = conformance::run(&ConcreteEvm::new(handlers), &fixtures);
+        print!("{}", report);
+        if !report.failures.is_empty() {
+            bail!("{} conformance tests failed", report.failures.len());
         }
+        return Ok(());
+    }
 
-        let prev_pc = context.pc;
-        (handler.handler)(handler, context, &mut sym_op);
+    // Read in the runtime binary
+    let binary = args.binary.clone().unwrap_or_default();
+    let runtime_string = fs::read_to_string(&binary)?
+        .trim_start_matches("0x")
+        .trim()
+        .to_string();
+    let runtime = hex::decode(runtime_string)?;
 
-        if opcode == JUMPI {
-            let mut new_constraints: Vec<Expr> = context.constraints.clone();
+    // Create the Symbolic Evm Context
+    let mut context = EvmContext::new(runtime.clone());
 
-            let condition_opcode: u8 = sym_args[1].sym_val.value as u8;
+    if let Some(input) = &args.concrete {
+        let result = ConcreteEvm::new(handlers).execute(&runtime, input);
+        match args.format {
+            Format::Json => println!("{}", report::execution_to_json(&result)),
+            _ => print!("{}", result),
+        }
+        return Ok(());
+    }
 
-            if is_relational(condition_opcode) || condition_opcode == ISZERO {
-                // at a branching point, convert to expression
-                let expression = term_to_expression(sym_args[1].clone());
+    let mut explorer = Explorer::new(handlers.clone());
+    explorer.verbose = args.format == Format::Text;
+    match &args.solver {
+        SolverChoice::Dl => {}
+        SolverChoice::Smt(command) => explorer.solver = Box::new(SmtProcessSolver::new(command)?),
+        SolverChoice::Bv(width) => explorer.solver = Box::new(BvSolver::new(*width)),
+    }
+    if args.replay {
+        explorer.replay = Some(ConcreteEvm::new(handlers));
+    }
+    explorer.overflow = args.overflow;
+    if args.format == Format::Dot {
+        explorer.tree = Some(DotTree::default());
+    }
 
-                if !expression.is_empty() {
-                    new_constraints.extend(expression);
-                    let result = solve(&new_constraints);
-                    println!("RESULT: {:?}", result);
-                } else {
-                    new_constraints = context.constraints.clone();
-                }
-            } else {
-                new_constraints = context.constraints.clone();
+    let abi = match &args.abi {
+        Some(path) => {
+            let abi = Abi::parse(&fs::read_to_string(path)?)?;
+            for name in &abi.skipped {
+                eprintln!("ABI: {} takes arguments we cannot model, left out", name);
             }
+            Some(abi)
+        }
+        None => None,
+    };
+    // Signature of a function, when the ABI has it
+    let signature = |function: &Function| {
+        abi.as_ref()
+            .and_then(|abi| abi.function(function.selector))
+            .map(|function| format!("{} ", function.signature()))
+            .unwrap_or_default()
+    };
 
-            let mut new_context = EvmContext {
-                code: context.code.clone(),
-                sym_stack: context.sym_stack.clone(),
-                pc: context.pc,
-                path: context.path.clone(),
-                constraints: new_constraints,
-                counter: context.counter,
-            };
-            context.counter = run(&mut new_context, handlers.clone());
-
-            context.pc = prev_pc + 1;
-        } else if [RETURN, REVERT, INVALID, STOP, SELFDESTRUCT].contains(&opcode) {
-            return context.counter;
+    let functions = dispatcher::functions(&runtime);
+    if args.list_functions {
+        for function in &functions {
+            println!("FUNCTION: {}{}", signature(function), function);
         }
+        return Ok(());
     }
-    context.counter
-}
+    let selector = match &args.function {
+        Some(function) => Some(parse_selector(function, abi.as_ref())?),
+        None => None,
+    };
+    let targets: Vec<Function> = match (selector, args.per_function) {
+        (Some(selector), _) => vec![functions
+            .iter()
+            .find(|function| function.selector == selector)
+            .copied()
+            .ok_or_else(|| {
+                anyhow!(
+                    "No function with selector 0x{} in the dispatcher",
+                    hex::encode(selector)
+                )
+            })?],
+        (None, true) if functions.is_empty() => bail!("No dispatcher found in the binary"),
+        (None, true) => functions,
+        (None, false) => Vec::new(),
+    };
 
-// Encode symbolic terms into DL expressions, when possible
-// For now, handle only LT and GT
-fn term_to_expression(term: Term) -> Vec<Expr> {
-    let opcode: u8 = term.sym_val.value as u8;
-    let args = term.args;
-
-    if opcode == LT {
-        if is_symbolic(&args[0]) && is_symbolic(&args[1]) {
-            return vec![Expr {
-                a: Variable(args[0].sym_val.value),
-                b: Variable(args[1].sym_val.value),
-                k: Constant(-1),
-            }];
-        } else if is_symbolic(&args[0]) && is_concrete(&args[1]) {
-            return vec![Expr {
-                a: Variable(args[0].sym_val.value),
-                b: Variable(0),
-                k: Constant((args[1].sym_val.value - 1) as i64),
-            }];
-        } else if is_concrete(&args[0]) && is_symbolic(&args[1]) {
-            return vec![Expr {
-                a: Variable(0),
-                b: Variable(args[1].sym_val.value),
-                k: Constant(-((args[1].sym_val.value + 1) as i64)),
-            }];
-        } else {
-            panic!("Constant LT");
+    // Text output is grouped per function, the other formats cover every explored path
+    if targets.is_empty() {
+        explorer.run(&mut context);
+        if args.format == Format::Text {
+            print_findings(&explorer.results, abi.as_ref());
         }
     }
-
-    if opcode == GT {
-        let swapped_term = Term {
-            sym_val: SymVal {
-                value: LT as u64,
-                kind: Kind::Symbolic,
-            },
-            args: vec![args[1].clone(), args[0].clone()],
+    for function in &targets {
+        let start = explorer.results.len();
+        if args.format == Format::Text {
+            println!("FUNCTION: {}{}", signature(function), function);
+        }
+        // DL tells calldata words apart by opcode alone, so ranges of one argument would
+        // constrain them all
+        let assumptions = match (&abi, &args.solver) {
+            (Some(abi), SolverChoice::Smt(_) | SolverChoice::Bv(_)) => abi
+                .function(function.selector)
+                .map(|function| function.assumptions())
+                .unwrap_or_default(),
+            _ => Vec::new(),
         };
-        return term_to_expression(swapped_term);
-    }
-
-    if opcode == EQ {
-        return vec![
-            Expr {
-                a: Variable(args[0].sym_val.value),
-                b: Variable(args[1].sym_val.value),
-                k: Constant(0),
-            },
-            Expr {
-                a: Variable(args[1].sym_val.value),
-                b: Variable(args[0].sym_val.value),
-                k: Constant(0),
-            },
-        ];
+        explorer.run_function(&mut context.clone(), function, &assumptions);
+        if args.format == Format::Text {
+            print_findings(&explorer.results[start..], abi.as_ref());
+        }
     }
 
-    if opcode == ISZERO && is_symbolic(&args[0]) {
-        let arg_opcode: u8 = args[0].sym_val.value as u8;
-        if arg_opcode == LT || arg_opcode == GT {
-            // negate child expressions
-            let child = term_to_expression(args[0].to_owned());
-            let first = child[0].to_owned();
-            return vec![Expr {
-                a: first.b,
-                b: first.a,
-                k: Constant(-(first.k.0 - (1_i64))),
-            }];
+    match args.format {
+        Format::Text => {}
+        Format::Json => println!("{}", report::to_json(&explorer.results, abi.as_ref())),
+        Format::Smt2 => print!("{}", to_smtlib(&explorer.results)),
+        Format::Coverage => print!("{}", Coverage::new(&runtime, &explorer.results, args.fork)),
+        Format::DeadCode => print!("{}", DeadCode::new(&runtime, &explorer.results, args.fork)),
+        Format::Dot => {
+            if let Some(tree) = &explorer.tree {
+                print!("{}", tree.to_dot());
+            }
+        }
+        Format::Sarif => {
+            let source_map = match &args.source_map {
+                Some(path) => Some(SourceMap::parse(&fs::read_to_string(path)?)?),
+                None => None,
+            };
+            let sources = args
+                .sources
+                .iter()
+                .map(|path| Ok((path.clone(), fs::read_to_string(path)?)))
+                .collect::<Result<Vec<(String, String)>>>()?;
+            let artifacts = Artifacts {
+                binary: &binary,
+                code: &runtime,
+                source_map: source_map.as_ref(),
+                sources: &sources,
+                abi: abi.as_ref(),
+            };
+            let log = to_sarif(&findings(&explorer.results), &artifacts);
+            println!("{}", serde_json::to_string_pretty(&log)?);
         }
     }
-
-    vec![]
-}
-
-fn is_symbolic(term: &Term) -> bool {
-    term.sym_val.kind == Kind::Symbolic
-}
-
-fn is_concrete(term: &Term) -> bool {
-    term.sym_val.kind == Kind::Concrete
-}
-
-fn search_path(path: &[usize], pc: &usize) -> bool {
-    false
+    Ok(())
 }
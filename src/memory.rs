@@ -0,0 +1,225 @@
+use crate::concrete::evaluate_term;
+use crate::sym_stack::Term;
+
+/// A write to memory. Offsets and sizes we cannot pin down clobber everything after them.
+#[derive(Debug, Clone)]
+struct MemoryWrite {
+    /// Offset written to, None when it is not a constant
+    offset: Option<usize>,
+    /// Number of bytes written, None when it is not a constant
+    size: Option<usize>,
+    /// The word written by MSTORE, or the byte by MSTORE8. None for copies we do not follow.
+    value: Option<Term>,
+}
+
+/// Symbolic byte-addressed memory. Writes are kept in order; MLOAD finds the word of the latest
+/// MSTORE to the same offset as long as nothing written since overlaps it.
+#[derive(Default, Debug, Clone)]
+pub struct SymMemory {
+    writes: Vec<MemoryWrite>,
+}
+
+// Offsets past this are not worth tracking, the EVM would run out of gas first
+const MAX_OFFSET: u64 = 1 << 32;
+
+// Longest data whose hash is keyed on its words
+const MAX_HASHED: usize = 32 * 16;
+
+// Stands in for writes that leave a word without a single value
+const UNTRACKED: MemoryWrite = MemoryWrite {
+    offset: None,
+    size: None,
+    value: None,
+};
+
+fn constant(term: &Term) -> Option<usize> {
+    let value = evaluate_term(term)?;
+    u64::try_from(value)
+        .ok()
+        .filter(|&value| value < MAX_OFFSET)
+        .map(|value| value as usize)
+}
+
+impl SymMemory {
+    /// MSTORE of `value` at `offset`
+    pub fn store(&mut self, offset: &Term, value: Term) {
+        self.writes.push(MemoryWrite {
+            offset: constant(offset),
+            size: Some(32),
+            value: Some(value),
+        });
+    }
+
+    /// MSTORE8 of the low byte of `value` at `offset`
+    pub fn store8(&mut self, offset: &Term, value: Term) {
+        self.writes.push(MemoryWrite {
+            offset: constant(offset),
+            size: Some(1),
+            value: Some(value),
+        });
+    }
+
+    /// A write of `size` bytes at `offset` whose contents we do not follow, such as CALLDATACOPY
+    pub fn clobber(&mut self, offset: &Term, size: &Term) {
+        let size = constant(size);
+        if size == Some(0) {
+            return;
+        }
+        self.writes.push(MemoryWrite {
+            offset: constant(offset),
+            size,
+            value: None,
+        });
+    }
+
+    /// Word last stored at `offset`, if it is still intact
+    pub fn load(&self, offset: &Term) -> Option<Term> {
+        self.latest_word(constant(offset)?)?.value.clone()
+    }
+
+    /// Words of the range `offset..offset + size`, when it is made of whole words that were each
+    /// written by a single MSTORE or never written at all
+    pub fn words(&self, offset: &Term, size: &Term) -> Option<Vec<Term>> {
+        let offset = constant(offset)?;
+        let size = constant(size)?;
+        if size % 32 != 0 || size > MAX_HASHED {
+            return None;
+        }
+        (0..size / 32)
+            .map(|i| match self.latest_word(offset + 32 * i) {
+                Some(write) => write.value.clone(),
+                None => Some(Term::constant(0)),
+            })
+            .collect()
+    }
+
+    // Write covering the word at `offset`, None when nothing was written there. A write that only
+    // partly covers the word has no single value for it.
+    fn latest_word(&self, offset: usize) -> Option<&MemoryWrite> {
+        for write in self.writes.iter().rev() {
+            match (write.offset, write.size) {
+                (Some(start), Some(size)) if start + size <= offset || offset + 32 <= start => {}
+                (Some(start), Some(32)) if start == offset => return Some(write),
+                _ => return Some(&UNTRACKED),
+            }
+        }
+        None
+    }
+
+    /// Bytes of the range `offset..offset + size`, None for bytes that are not known constants.
+    /// Memory nobody wrote to is zero.
+    pub fn bytes(&self, offset: &Term, size: &Term) -> Option<Vec<Option<u8>>> {
+        let offset = constant(offset)?;
+        let size = constant(size)?;
+        let mut bytes: Vec<Option<u8>> = vec![Some(0); size];
+
+        for write in &self.writes {
+            let (start, length) = match (write.offset, write.size) {
+                (Some(start), Some(length)) => (start, length),
+                // Anything could have been written anywhere
+                _ => {
+                    bytes.fill(None);
+                    continue;
+                }
+            };
+            let value = write
+                .value
+                .as_ref()
+                .and_then(evaluate_term)
+                .map(|value| value.to_be_bytes::<32>());
+            for i in 0..length {
+                let address = start + i;
+                if address < offset || address >= offset + size {
+                    continue;
+                }
+                bytes[address - offset] = value.map(|word| word[32 - length + i]);
+            }
+        }
+        Some(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opcodes::CALLDATALOAD;
+    use crate::sym_stack::{Kind, SymVal};
+
+    fn constant(value: u64) -> Term {
+        Term {
+            sym_val: SymVal {
+                value,
+                kind: Kind::Concrete,
+            },
+            args: Vec::new(),
+        }
+    }
+
+    fn op(opcode: u8, args: Vec<Term>) -> Term {
+        Term {
+            sym_val: SymVal {
+                value: opcode as u64,
+                kind: Kind::Symbolic,
+            },
+            args,
+        }
+    }
+
+    fn at(offset: u64) -> Term {
+        constant(offset)
+    }
+
+    fn input() -> Term {
+        op(CALLDATALOAD, vec![at(0)])
+    }
+
+    #[test]
+    fn loads_see_the_latest_intact_store() {
+        let mut memory = SymMemory::default();
+        memory.store(&at(0), at(1));
+        memory.store(&at(0), input());
+        assert_eq!(memory.load(&at(0)), Some(input()));
+
+        // Next to the word, not over it
+        memory.store(&at(32), at(2));
+        assert_eq!(memory.load(&at(0)), Some(input()));
+        // Partly over it
+        memory.store8(&at(31), at(3));
+        assert_eq!(memory.load(&at(0)), None);
+        assert_eq!(memory.load(&at(32)), Some(at(2)));
+    }
+
+    #[test]
+    fn unknown_offsets_clobber_everything() {
+        let mut memory = SymMemory::default();
+        memory.store(&at(0), at(1));
+        memory.store(&input(), at(2));
+        assert_eq!(memory.load(&at(0)), None);
+        assert_eq!(memory.load(&input()), None);
+        assert_eq!(memory.bytes(&at(0), &at(1)), Some(vec![None]));
+    }
+
+    #[test]
+    fn words_of_hashed_ranges() {
+        let mut memory = SymMemory::default();
+        memory.store(&at(0), input());
+        assert_eq!(memory.words(&at(0), &at(64)), Some(vec![input(), at(0)]));
+        assert_eq!(memory.words(&at(0), &at(33)), None);
+
+        memory.clobber(&at(40), &at(0));
+        assert!(memory.words(&at(32), &at(32)).is_some());
+        memory.clobber(&at(40), &at(4));
+        assert_eq!(memory.words(&at(32), &at(32)), None);
+    }
+
+    #[test]
+    fn bytes_of_constant_writes() {
+        let mut memory = SymMemory::default();
+        memory.store(&at(0), at(0x0102));
+        memory.store8(&at(32), at(0xffab));
+        memory.store(&at(64), input());
+        let bytes = memory.bytes(&at(30), &at(4)).unwrap();
+        assert_eq!(bytes, vec![Some(1), Some(2), Some(0xab), Some(0)]);
+        assert_eq!(memory.bytes(&at(64), &at(1)), Some(vec![None]));
+    }
+}
@@ -63,6 +63,8 @@ pub const GASLIMIT: u8 = 0x45;
 pub const CHAINID: u8 = 0x46;
 pub const SELFBALANCE: u8 = 0x47;
 pub const BASEFEE: u8 = 0x48;
+pub const BLOBHASH: u8 = 0x49;
+pub const BLOBBASEFEE: u8 = 0x4a;
 
 // Memory regions
 pub const POP: u8 = 0x50;
@@ -77,8 +79,12 @@ pub const PC: u8 = 0x58;
 pub const MSIZE: u8 = 0x59;
 pub const GAS: u8 = 0x5a;
 pub const JUMPDEST: u8 = 0x5b;
+pub const TLOAD: u8 = 0x5c;
+pub const TSTORE: u8 = 0x5d;
+pub const MCOPY: u8 = 0x5e;
 
 // Push operations
+pub const PUSH0: u8 = 0x5f;
 pub const PUSH1: u8 = 0x60;
 pub const PUSH2: u8 = 0x61;
 pub const PUSH3: u8 = 0x62;
@@ -170,3 +176,210 @@ pub const SELFDESTRUCT: u8 = 0xff;
 pub fn is_relational(opcode: u8) -> bool {
     opcode == LT || opcode == SLT || opcode == GT || opcode == SGT
 }
+
+pub fn is_push(opcode: u8) -> bool {
+    (PUSH0..=PUSH32).contains(&opcode)
+}
+
+/// Number of immediate bytes that follow a PUSH opcode in the bytecode
+pub fn push_size(opcode: u8) -> usize {
+    if (PUSH1..=PUSH32).contains(&opcode) {
+        (opcode - PUSH1) as usize + 1
+    } else {
+        0
+    }
+}
+
+/// Group an opcode belongs to, following the sections of the opcode table
+pub fn opcode_group(opcode: u8) -> &'static str {
+    match opcode {
+        _ if opcode_name(opcode) == "UNKNOWN" => "undefined",
+        STOP..=SIGNEXTEND => "arithmetic",
+        LT..=SAR => "comparison & bitwise",
+        SHA3 => "sha3",
+        ADDRESS..=EXTCODEHASH => "environment",
+        BLOCKHASH..=BLOBBASEFEE => "block",
+        POP..=MCOPY => "stack, memory, storage & flow",
+        PUSH0..=PUSH32 => "push",
+        DUP1..=DUP16 => "dup",
+        SWAP1..=SWAP16 => "swap",
+        LOG0..=LOG4 => "logging",
+        _ => "system",
+    }
+}
+
+pub fn is_terminating(opcode: u8) -> bool {
+    [RETURN, REVERT, INVALID, STOP, SELFDESTRUCT].contains(&opcode)
+}
+
+/// Opcodes that access storage, call out or destroy the account
+pub fn is_effect(opcode: u8) -> bool {
+    [
+        SLOAD,
+        SSTORE,
+        CREATE,
+        CALL,
+        CALLCODE,
+        DELEGATECALL,
+        CREATE2,
+        STATICCALL,
+        SELFDESTRUCT,
+    ]
+    .contains(&opcode)
+}
+
+/// Mnemonic of an opcode, or "UNKNOWN" for undefined ones
+pub fn opcode_name(opcode: u8) -> &'static str {
+    match opcode {
+        STOP => "STOP",
+        ADD => "ADD",
+        MUL => "MUL",
+        SUB => "SUB",
+        DIV => "DIV",
+        SDIV => "SDIV",
+        MOD => "MOD",
+        SMOD => "SMOD",
+        ADDMOD => "ADDMOD",
+        MULMOD => "MULMOD",
+        EXP => "EXP",
+        SIGNEXTEND => "SIGNEXTEND",
+        LT => "LT",
+        GT => "GT",
+        SLT => "SLT",
+        SGT => "SGT",
+        EQ => "EQ",
+        ISZERO => "ISZERO",
+        AND => "AND",
+        OR => "OR",
+        XOR => "XOR",
+        NOT => "NOT",
+        BYTE => "BYTE",
+        SHL => "SHL",
+        SHR => "SHR",
+        SAR => "SAR",
+        SHA3 => "SHA3",
+        ADDRESS => "ADDRESS",
+        BALANCE => "BALANCE",
+        ORIGIN => "ORIGIN",
+        CALLER => "CALLER",
+        CALLVALUE => "CALLVALUE",
+        CALLDATALOAD => "CALLDATALOAD",
+        CALLDATASIZE => "CALLDATASIZE",
+        CALLDATACOPY => "CALLDATACOPY",
+        CODESIZE => "CODESIZE",
+        CODECOPY => "CODECOPY",
+        GASPRICE => "GASPRICE",
+        EXTCODESIZE => "EXTCODESIZE",
+        EXTCODECOPY => "EXTCODECOPY",
+        RETURNDATASIZE => "RETURNDATASIZE",
+        RETURNDATACOPY => "RETURNDATACOPY",
+        EXTCODEHASH => "EXTCODEHASH",
+        BLOCKHASH => "BLOCKHASH",
+        COINBASE => "COINBASE",
+        TIMESTAMP => "TIMESTAMP",
+        NUMBER => "NUMBER",
+        DIFFICULTY => "DIFFICULTY",
+        GASLIMIT => "GASLIMIT",
+        CHAINID => "CHAINID",
+        SELFBALANCE => "SELFBALANCE",
+        BASEFEE => "BASEFEE",
+        BLOBHASH => "BLOBHASH",
+        BLOBBASEFEE => "BLOBBASEFEE",
+        POP => "POP",
+        MLOAD => "MLOAD",
+        MSTORE => "MSTORE",
+        MSTORE8 => "MSTORE8",
+        SLOAD => "SLOAD",
+        SSTORE => "SSTORE",
+        JUMP => "JUMP",
+        JUMPI => "JUMPI",
+        PC => "PC",
+        MSIZE => "MSIZE",
+        GAS => "GAS",
+        JUMPDEST => "JUMPDEST",
+        TLOAD => "TLOAD",
+        TSTORE => "TSTORE",
+        MCOPY => "MCOPY",
+        PUSH0 => "PUSH0",
+        PUSH1 => "PUSH1",
+        PUSH2 => "PUSH2",
+        PUSH3 => "PUSH3",
+        PUSH4 => "PUSH4",
+        PUSH5 => "PUSH5",
+        PUSH6 => "PUSH6",
+        PUSH7 => "PUSH7",
+        PUSH8 => "PUSH8",
+        PUSH9 => "PUSH9",
+        PUSH10 => "PUSH10",
+        PUSH11 => "PUSH11",
+        PUSH12 => "PUSH12",
+        PUSH13 => "PUSH13",
+        PUSH14 => "PUSH14",
+        PUSH15 => "PUSH15",
+        PUSH16 => "PUSH16",
+        PUSH17 => "PUSH17",
+        PUSH18 => "PUSH18",
+        PUSH19 => "PUSH19",
+        PUSH20 => "PUSH20",
+        PUSH21 => "PUSH21",
+        PUSH22 => "PUSH22",
+        PUSH23 => "PUSH23",
+        PUSH24 => "PUSH24",
+        PUSH25 => "PUSH25",
+        PUSH26 => "PUSH26",
+        PUSH27 => "PUSH27",
+        PUSH28 => "PUSH28",
+        PUSH29 => "PUSH29",
+        PUSH30 => "PUSH30",
+        PUSH31 => "PUSH31",
+        PUSH32 => "PUSH32",
+        DUP1 => "DUP1",
+        DUP2 => "DUP2",
+        DUP3 => "DUP3",
+        DUP4 => "DUP4",
+        DUP5 => "DUP5",
+        DUP6 => "DUP6",
+        DUP7 => "DUP7",
+        DUP8 => "DUP8",
+        DUP9 => "DUP9",
+        DUP10 => "DUP10",
+        DUP11 => "DUP11",
+        DUP12 => "DUP12",
+        DUP13 => "DUP13",
+        DUP14 => "DUP14",
+        DUP15 => "DUP15",
+        DUP16 => "DUP16",
+        SWAP1 => "SWAP1",
+        SWAP2 => "SWAP2",
+        SWAP3 => "SWAP3",
+        SWAP4 => "SWAP4",
+        SWAP5 => "SWAP5",
+        SWAP6 => "SWAP6",
+        SWAP7 => "SWAP7",
+        SWAP8 => "SWAP8",
+        SWAP9 => "SWAP9",
+        SWAP10 => "SWAP10",
+        SWAP11 => "SWAP11",
+        SWAP12 => "SWAP12",
+        SWAP13 => "SWAP13",
+        SWAP14 => "SWAP14",
+        SWAP15 => "SWAP15",
+        SWAP16 => "SWAP16",
+        LOG0 => "LOG0",
+        LOG1 => "LOG1",
+        LOG2 => "LOG2",
+        LOG3 => "LOG3",
+        LOG4 => "LOG4",
+        CREATE => "CREATE",
+        CALL => "CALL",
+        CALLCODE => "CALLCODE",
+        RETURN => "RETURN",
+        DELEGATECALL => "DELEGATECALL",
+        CREATE2 => "CREATE2",
+        STATICCALL => "STATICCALL",
+        REVERT => "REVERT",
+        INVALID => "INVALID",
+        SELFDESTRUCT => "SELFDESTRUCT",
+        _ => "UNKNOWN",
+    }
+}
@@ -0,0 +1,113 @@
+use crate::opcodes::*;
+use crate::solve::Model;
+use crate::sym_stack::{is_concrete, Kind, Term};
+
+/// An ADD, SUB or MUL that can wrap around on a path that ends successfully
+#[derive(Debug, Clone)]
+pub struct Overflow {
+    /// Pc of the arithmetic instruction
+    pub pc: usize,
+    pub opcode: u8,
+    /// Condition that holds exactly when the operation wraps
+    pub condition: Term,
+    /// Assignment under which the path is taken and the operation wraps
+    pub model: Option<Model>,
+}
+
+/// Whether `term` is arithmetic worth checking: an ADD, SUB or MUL with a symbolic operand
+pub fn is_checked(term: &Term) -> bool {
+    matches!(term.sym_val.value as u8, ADD | SUB | MUL)
+        && term.sym_val.kind == Kind::Symbolic
+        && !term.args.iter().all(is_concrete)
+}
+
+/// Condition, as a term, that is non-zero exactly when the 256-bit operation `term` wraps
+pub fn wrap_condition(term: &Term) -> Option<Term> {
+    let (a, b) = match term.args.as_slice() {
+        [a, b] => (a.clone(), b.clone()),
+        _ => return None,
+    };
+    let condition = match term.sym_val.value as u8 {
+        // a + b wrapped when the sum is below an operand
+        ADD => Term::op(LT, vec![term.clone(), a]),
+        // a - b wraps when b is larger
+        SUB => Term::op(LT, vec![a, b]),
+        // a * b wrapped when a != 0 and (a * b) / a != b
+        MUL => Term::op(
+            AND,
+            vec![
+                Term::op(ISZERO, vec![Term::op(ISZERO, vec![a.clone()])]),
+                Term::op(
+                    ISZERO,
+                    vec![Term::op(EQ, vec![Term::op(DIV, vec![term.clone(), a]), b])],
+                ),
+            ],
+        ),
+        _ => return None,
+    };
+    Some(condition)
+}
+
+/// How the operation wraps, for messages
+pub fn describe(opcode: u8) -> &'static str {
+    match opcode {
+        ADD => "ADD can overflow 256 bits",
+        SUB => "SUB can underflow below zero",
+        MUL => "MUL can overflow 256 bits",
+        _ => "Arithmetic can wrap around",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concrete::evaluate_term;
+    use ruint::aliases::U256;
+
+    #[test]
+    fn wrap_conditions_hold_exactly_when_the_operation_wraps() {
+        let values = [
+            U256::ZERO,
+            U256::from(1),
+            U256::from(2),
+            U256::from(3),
+            U256::MAX >> 128,
+            U256::from(1) << 128,
+            U256::MAX >> 1,
+            U256::MAX - U256::from(1),
+            U256::MAX,
+        ];
+        for &a in &values {
+            for &b in &values {
+                for (opcode, wraps) in [
+                    (ADD, a.overflowing_add(b).1),
+                    (SUB, a.overflowing_sub(b).1),
+                    (MUL, a.overflowing_mul(b).1),
+                ] {
+                    let term = Term::op(opcode, vec![Term::word(a), Term::word(b)]);
+                    let condition = wrap_condition(&term).unwrap();
+                    let holds = !evaluate_term(&condition).unwrap().is_zero();
+                    assert_eq!(holds, wraps, "{} {} {}", opcode_name(opcode), a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn only_symbolic_arithmetic_is_checked() {
+        let input = Term::op(CALLDATALOAD, vec![Term::constant(0)]);
+        assert!(is_checked(&Term::op(
+            ADD,
+            vec![input.clone(), Term::constant(1)]
+        )));
+        assert!(!is_checked(&Term::op(
+            DIV,
+            vec![input.clone(), Term::constant(1)]
+        )));
+        assert!(!is_checked(&Term::op(
+            MUL,
+            vec![Term::constant(2), Term::constant(3)]
+        )));
+        assert!(wrap_condition(&Term::op(ISZERO, vec![input])).is_none());
+    }
+}
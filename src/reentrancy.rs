@@ -0,0 +1,155 @@
+use crate::handlers::Effect;
+use crate::opcodes::*;
+use crate::sym_stack::{is_concrete, mentions, Term};
+
+/// Gas stipend of transfer and send, too little to re-enter with
+const STIPEND: u64 = 2300;
+
+/// The classic reentrancy shape on one path: a slot read before an external call and written
+/// after it, so a re-entered call still sees the old value
+#[derive(Debug, Clone)]
+pub struct Reentrancy {
+    pub call_pc: usize,
+    pub read_pc: usize,
+    pub write_pc: usize,
+    /// Slot read and written, as a term
+    pub slot: Term,
+}
+
+// The gas argument hands the callee enough gas to call back
+fn forwards_gas(gas: &Term) -> bool {
+    match is_concrete(gas) {
+        true => gas.sym_val.value > STIPEND,
+        false => mentions(gas, GAS),
+    }
+}
+
+// Values the sender of the transaction picks
+fn attacker_influenced(term: &Term) -> bool {
+    [CALLER, ORIGIN, CALLDATALOAD]
+        .iter()
+        .any(|&source| mentions(term, source))
+}
+
+/// Reentrancy shapes among the effects of a path, in path order. A CALL counts when it forwards
+/// gas and its target or value is influenced by the sender; a read and write of the same slot
+/// around it make a finding.
+pub fn reentrancies(effects: &[Effect]) -> Vec<Reentrancy> {
+    let mut found = Vec::new();
+    for (i, call) in effects.iter().enumerate() {
+        if call.opcode != CALL
+            || !forwards_gas(&call.args[0])
+            || !(attacker_influenced(&call.args[1]) || attacker_influenced(&call.args[2]))
+        {
+            continue;
+        }
+        for write in effects[i + 1..].iter().filter(|e| e.opcode == SSTORE) {
+            let read = effects[..i]
+                .iter()
+                .find(|e| e.opcode == SLOAD && e.args[0] == write.args[0]);
+            if let Some(read) = read {
+                found.push(Reentrancy {
+                    call_pc: call.pc,
+                    read_pc: read.pc,
+                    write_pc: write.pc,
+                    slot: write.args[0].clone(),
+                });
+            }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sym_stack::{Kind, SymVal};
+
+    fn constant(value: u64) -> Term {
+        Term {
+            sym_val: SymVal {
+                value,
+                kind: Kind::Concrete,
+            },
+            args: Vec::new(),
+        }
+    }
+
+    fn op(opcode: u8, args: Vec<Term>) -> Term {
+        Term {
+            sym_val: SymVal {
+                value: opcode as u64,
+                kind: Kind::Symbolic,
+            },
+            args,
+        }
+    }
+
+    fn effect(pc: usize, opcode: u8, args: Vec<Term>) -> Effect {
+        Effect {
+            pc,
+            opcode,
+            args,
+            result: None,
+        }
+    }
+
+    fn read(opcode: u8) -> Term {
+        op(opcode, Vec::new())
+    }
+
+    // CALL(gas, to, value) with the rest of its arguments left out
+    fn call(pc: usize, gas: Term, to: Term, value: Term) -> Effect {
+        effect(pc, CALL, vec![gas, to, value])
+    }
+
+    fn withdraw(gas: Term, to: Term) -> Vec<Effect> {
+        let slot = constant(1);
+        vec![
+            effect(1, SLOAD, vec![slot.clone()]),
+            call(2, gas, to, constant(5)),
+            effect(3, SSTORE, vec![slot, constant(0)]),
+        ]
+    }
+
+    #[test]
+    fn write_after_a_call_to_the_sender_is_found() {
+        let found = reentrancies(&withdraw(read(GAS), read(CALLER)));
+        assert_eq!(found.len(), 1);
+        let reentrancy = &found[0];
+        assert_eq!(
+            (reentrancy.read_pc, reentrancy.call_pc, reentrancy.write_pc),
+            (1, 2, 3)
+        );
+        assert_eq!(reentrancy.slot, constant(1));
+    }
+
+    #[test]
+    fn stipend_calls_and_fixed_targets_cannot_re_enter() {
+        assert!(reentrancies(&withdraw(constant(STIPEND), read(CALLER))).is_empty());
+        assert_eq!(
+            reentrancies(&withdraw(constant(STIPEND + 1), read(CALLER))).len(),
+            1
+        );
+        assert!(reentrancies(&withdraw(read(GAS), constant(0xbeef))).is_empty());
+    }
+
+    #[test]
+    fn the_slot_must_be_read_before_and_written_after() {
+        let slot = constant(1);
+        let call = call(2, read(GAS), read(CALLER), constant(0));
+        // Checks-effects-interactions
+        let safe = vec![
+            effect(0, SLOAD, vec![slot.clone()]),
+            effect(1, SSTORE, vec![slot.clone(), constant(0)]),
+            call.clone(),
+        ];
+        assert!(reentrancies(&safe).is_empty());
+        let other_slot = vec![
+            effect(0, SLOAD, vec![slot]),
+            call,
+            effect(3, SSTORE, vec![constant(2), constant(0)]),
+        ];
+        assert!(reentrancies(&other_slot).is_empty());
+    }
+}
@@ -0,0 +1,343 @@
+use ruint::aliases::U256;
+
+use crate::concrete::{arithmetic, ConcreteEvm, ConcreteInput, Env};
+use crate::explorer::PathResult;
+use crate::opcodes::*;
+use crate::solve::Model;
+use crate::sym_stack::{is_concrete, Term};
+
+/// Outcome of replaying the model of a satisfiable path on the concrete interpreter
+#[derive(Debug, Clone, PartialEq)]
+pub enum Replay {
+    /// The concrete run took every JUMPI the same way and ended at the same pc
+    Confirmed,
+    /// The concrete run decided a JUMPI differently, or stopped before reaching it
+    Diverged {
+        /// Pc of the JUMPI the path expected next
+        pc: usize,
+        /// Direction the path took
+        expected: bool,
+        /// Direction the concrete run took, None when it took a different JUMPI or none at all
+        actual: Option<bool>,
+    },
+    /// Every JUMPI matched but the concrete run ended somewhere else
+    HaltMismatch {
+        expected_pc: usize,
+        actual_pc: usize,
+    },
+}
+
+/// Concrete input under which the path condition holds, according to the model
+pub fn model_to_input(result: &PathResult, model: &Model) -> ConcreteInput {
+    let mut input = conditions_to_input(
+        result.branches.iter().map(|branch| &branch.condition),
+        model,
+    );
+    if let Some(selector) = result.selector {
+        set_selector(&mut input, selector);
+    }
+    input
+}
+
+/// Put the selector of the function being called at the start of the calldata
+pub fn set_selector(input: &mut ConcreteInput, selector: [u8; 4]) {
+    if input.calldata.len() < 4 {
+        input.calldata.resize(4, 0);
+    }
+    input.calldata[..4].copy_from_slice(&selector);
+}
+
+/// Concrete input under which the conditions hold, according to the model. Model entries are
+/// matched to the input terms of the conditions either by the full term, as bit-vector solvers
+/// name them, or by the opcode alone, as the DL encoding does. Inputs the model says nothing
+/// about are left at their defaults.
+pub fn conditions_to_input<'a>(
+    conditions: impl IntoIterator<Item = &'a Term>,
+    model: &Model,
+) -> ConcreteInput {
+    let mut sources: Vec<&Term> = Vec::new();
+    for condition in conditions {
+        collect_sources(condition, &mut sources);
+    }
+
+    let mut input = ConcreteInput::default();
+    // Sizes first so that the loads below can extend the calldata past them
+    let ordered = sources
+        .iter()
+        .filter(|term| term.sym_val.value as u8 == CALLDATASIZE)
+        .chain(
+            sources
+                .iter()
+                .filter(|term| term.sym_val.value as u8 != CALLDATASIZE),
+        );
+    for term in ordered {
+        let opcode = term.sym_val.value as u8;
+        let value = match model
+            .get(&term.to_string())
+            .or_else(|| model.get(opcode_name(opcode)))
+        {
+            Some(value) => *value,
+            None => continue,
+        };
+        assign(&mut input, term, value, model);
+    }
+    input
+}
+
+/// Run the path's model concretely and check that execution follows the same path
+pub fn replay(evm: &ConcreteEvm, code: &[u8], result: &PathResult, model: &Model) -> Replay {
+    let input = model_to_input(result, model);
+    let execution = evm.execute(code, &input);
+
+    // Direction of every JUMPI the concrete run executed; the condition sits below the target
+    let decisions: Vec<(usize, bool)> = execution
+        .trace
+        .iter()
+        .filter(|step| step.opcode == JUMPI && step.stack.len() >= 2)
+        .map(|step| (step.pc, !step.stack[step.stack.len() - 2].is_zero()))
+        .collect();
+
+    for (i, branch) in result.branches.iter().enumerate() {
+        match decisions.get(i) {
+            Some(&(pc, taken)) if pc == branch.pc && taken == branch.taken => continue,
+            Some(&(pc, taken)) if pc == branch.pc => {
+                return Replay::Diverged {
+                    pc: branch.pc,
+                    expected: branch.taken,
+                    actual: Some(taken),
+                }
+            }
+            _ => {
+                return Replay::Diverged {
+                    pc: branch.pc,
+                    expected: branch.taken,
+                    actual: None,
+                }
+            }
+        }
+    }
+
+    if execution.halt_pc != result.halt_pc {
+        return Replay::HaltMismatch {
+            expected_pc: result.halt_pc,
+            actual_pc: execution.halt_pc,
+        };
+    }
+    Replay::Confirmed
+}
+
+// Input terms of a condition: reads of calldata, storage, the environment and call outcomes
+fn collect_sources<'a>(term: &'a Term, sources: &mut Vec<&'a Term>) {
+    if is_concrete(term) {
+        return;
+    }
+    if is_source(term) && !sources.contains(&term) {
+        sources.push(term);
+    }
+    // Where an input is read from can depend on other inputs, like the offset of an ABI argument
+    for arg in &term.args {
+        collect_sources(arg, sources);
+    }
+}
+
+fn is_source(term: &Term) -> bool {
+    let opcode = term.sym_val.value as u8;
+    match opcode {
+        // Storage can only be set up at slots known up front, while calldata reads, even at offsets
+        // computed from other inputs, are placed once the model gives the offset
+        SLOAD => term.args.len() == 1 && is_concrete(&term.args[0]),
+        CALLDATALOAD => true,
+        CALLDATASIZE | CALL | CALLCODE | DELEGATECALL | STATICCALL => true,
+        _ => env_field(&mut Env::default(), opcode).is_some(),
+    }
+}
+
+/// Environment value an opcode reads, if it reads one straight from the input
+pub(crate) fn env_field(env: &mut Env, opcode: u8) -> Option<&mut U256> {
+    let field = match opcode {
+        ADDRESS => &mut env.address,
+        ORIGIN => &mut env.origin,
+        CALLER => &mut env.caller,
+        CALLVALUE => &mut env.callvalue,
+        GASPRICE => &mut env.gasprice,
+        COINBASE => &mut env.coinbase,
+        TIMESTAMP => &mut env.timestamp,
+        NUMBER => &mut env.number,
+        DIFFICULTY => &mut env.prevrandao,
+        GASLIMIT => &mut env.gaslimit,
+        CHAINID => &mut env.chainid,
+        SELFBALANCE => &mut env.balance,
+        BASEFEE => &mut env.basefee,
+        BLOBBASEFEE => &mut env.blobbasefee,
+        GAS => &mut env.gas,
+        _ => return None,
+    };
+    Some(field)
+}
+
+// Value of a term under the model: its own entry, or computed from the values of its arguments
+fn evaluate(term: &Term, model: &Model) -> Option<U256> {
+    if is_concrete(term) {
+        return Some(U256::from(term.sym_val.value));
+    }
+    if let Some(value) = model.get(&term.to_string()) {
+        return Some(*value);
+    }
+    let args = term
+        .args
+        .iter()
+        .map(|arg| evaluate(arg, model))
+        .collect::<Option<Vec<U256>>>()?;
+    arithmetic(term.sym_val.value as u8, &args)
+}
+
+fn assign(input: &mut ConcreteInput, term: &Term, value: U256, model: &Model) {
+    let opcode = term.sym_val.value as u8;
+    match opcode {
+        CALLDATALOAD => {
+            let offset = match evaluate(&term.args[0], model).map(usize::try_from) {
+                Some(Ok(offset)) if offset <= 1 << 16 => offset,
+                _ => return,
+            };
+            if input.calldata.len() < offset + 32 {
+                input.calldata.resize(offset + 32, 0);
+            }
+            input.calldata[offset..offset + 32].copy_from_slice(&value.to_be_bytes::<32>());
+        }
+        CALLDATASIZE => {
+            // Anything larger is not a calldata size the model could mean
+            if let Ok(size) = usize::try_from(value) {
+                if size <= 1 << 16 {
+                    input.calldata.resize(size, 0);
+                }
+            }
+        }
+        SLOAD => {
+            input
+                .storage
+                .insert(U256::from(term.args[0].sym_val.value), value);
+        }
+        CALL | CALLCODE | DELEGATECALL | STATICCALL => input.env.call_success = !value.is_zero(),
+        _ => {
+            if let Some(field) = env_field(&mut input.env, opcode) {
+                *field = value;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitblast::BvSolver;
+    use crate::explorer::Explorer;
+    use crate::handlers::{sym_handlers, EvmContext};
+    use crate::hardfork::Hardfork;
+    use crate::solve::DLResult;
+    use crate::sym_stack::{Kind, SymVal};
+
+    fn constant(value: u64) -> Term {
+        Term {
+            sym_val: SymVal {
+                value,
+                kind: Kind::Concrete,
+            },
+            args: Vec::new(),
+        }
+    }
+
+    fn op(opcode: u8, args: Vec<Term>) -> Term {
+        Term {
+            sym_val: SymVal {
+                value: opcode as u64,
+                kind: Kind::Symbolic,
+            },
+            args,
+        }
+    }
+
+    fn calldataload(offset: Term) -> Term {
+        op(CALLDATALOAD, vec![offset])
+    }
+
+    #[test]
+    fn models_name_inputs_by_term() {
+        let word = calldataload(constant(4));
+        let slot = op(SLOAD, vec![constant(3)]);
+        let caller = op(CALLER, Vec::new());
+        let condition = op(
+            EQ,
+            vec![op(ADD, vec![word.clone(), slot.clone()]), caller.clone()],
+        );
+        let model = Model::from([
+            (word.to_string(), U256::from(7)),
+            (slot.to_string(), U256::from(8)),
+            (caller.to_string(), U256::from(15)),
+        ]);
+
+        let input = conditions_to_input([&condition], &model);
+        assert_eq!(input.calldata.len(), 36);
+        assert_eq!(U256::from_be_slice(&input.calldata[4..]), U256::from(7));
+        assert_eq!(input.storage[&U256::from(3)], U256::from(8));
+        assert_eq!(input.env.caller, U256::from(15));
+    }
+
+    #[test]
+    fn models_name_inputs_by_opcode() {
+        // The DL encoding only knows the opcode
+        let condition = op(LT, vec![op(CALLVALUE, Vec::new()), constant(5)]);
+        let model = Model::from([("CALLVALUE".to_string(), U256::from(4))]);
+        let input = conditions_to_input([&condition], &model);
+        assert_eq!(input.env.callvalue, U256::from(4));
+    }
+
+    #[test]
+    fn offsets_read_from_calldata_place_the_word() {
+        // An ABI dynamic argument: the word at 4 + the offset stored at 4
+        let offset = calldataload(constant(4));
+        let length = calldataload(op(ADD, vec![constant(4), offset.clone()]));
+        let condition = op(GT, vec![length.clone(), constant(0)]);
+        let model = Model::from([
+            (offset.to_string(), U256::from(0x20)),
+            (length.to_string(), U256::from(3)),
+        ]);
+        let input = conditions_to_input([&condition], &model);
+        assert_eq!(
+            U256::from_be_slice(&input.calldata[4..36]),
+            U256::from(0x20)
+        );
+        assert_eq!(U256::from_be_slice(&input.calldata[36..68]), U256::from(3));
+    }
+
+    #[test]
+    fn models_replay_down_their_path() {
+        // if (calldataload(0) == 10) jump
+        let code = hex::decode("600035600a14600a57005b00").unwrap();
+        let mut explorer = Explorer::new(sym_handlers(Hardfork::default()));
+        explorer.solver = Box::new(BvSolver::new(256));
+        explorer.run(&mut EvmContext::new(code.clone()));
+        let evm = ConcreteEvm::new(sym_handlers(Hardfork::default()));
+
+        assert_eq!(explorer.results.len(), 2);
+        for result in &explorer.results {
+            assert_eq!(result.result, DLResult::Sat);
+            let model = result.model.as_ref().unwrap();
+            assert_eq!(replay(&evm, &code, result, model), Replay::Confirmed);
+        }
+
+        let taken = explorer
+            .results
+            .iter()
+            .find(|result| result.branches[0].taken)
+            .unwrap();
+        let wrong = Model::from([(calldataload(constant(0)).to_string(), U256::from(11))]);
+        assert_eq!(
+            replay(&evm, &code, taken, &wrong),
+            Replay::Diverged {
+                pc: 8,
+                expected: true,
+                actual: Some(false),
+            }
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use ruint::aliases::U256;
+use serde_json::{json, Map, Value};
+
+use crate::abi::Abi;
+use crate::concrete::ExecutionResult;
+use crate::explorer::PathResult;
+use crate::failures::classify;
+use crate::opcodes::opcode_name;
+use crate::solve::{DLResult, Model};
+use crate::transaction::Transaction;
+
+/// Model as a JSON object, with values as decimal strings since they do not fit JSON numbers
+pub fn model_to_json(model: &Model) -> Value {
+    Value::Object(
+        model
+            .iter()
+            .map(|(name, value)| (name.clone(), json!(value.to_string())))
+            .collect::<Map<String, Value>>(),
+    )
+}
+
+/// Transaction as a JSON object, with the devnet commands that send it
+pub fn transaction_to_json(transaction: &Transaction) -> Value {
+    let word = |value: &U256| json!(format!("{:#x}", value));
+    let environment: Map<String, Value> = transaction
+        .environment
+        .iter()
+        .map(|(opcode, value)| (opcode_name(*opcode).to_string(), word(value)))
+        .collect();
+    let storage: Vec<Value> = transaction
+        .storage
+        .iter()
+        .map(|(slot, value)| json!({ "slot": word(slot), "value": word(value) }))
+        .collect();
+
+    json!({
+        "calldata": format!("0x{}", hex::encode(&transaction.calldata)),
+        "caller": word(&transaction.caller),
+        "value": word(&transaction.value),
+        "environment": environment,
+        "storage": storage,
+        "calls_succeed": transaction.calls_succeed,
+        "commands": transaction.commands(),
+    })
+}
+
+/// JSON record describing one explored path. Calldata of the path's transaction is padded to a
+/// complete encoding when the ABI has the function it calls.
+pub fn path_to_json(result: &PathResult, abi: Option<&Abi>) -> Value {
+    let branches: Vec<Value> = result
+        .branches
+        .iter()
+        .map(|branch| {
+            json!({
+                "pc": branch.pc,
+                "taken": branch.taken,
+                "condition": branch.condition.to_string(),
+            })
+        })
+        .collect();
+
+    let constraints: Vec<String> = result
+        .constraints
+        .iter()
+        .map(|expr| expr.to_string())
+        .collect();
+
+    let model = result.model.as_ref().map(model_to_json);
+    let transaction = result.model.as_ref().map(|model| {
+        let mut transaction = Transaction::on_path(result, model);
+        if let Some(abi) = abi {
+            transaction.complete(abi);
+        }
+        transaction_to_json(&transaction)
+    });
+
+    let overflows: Vec<Value> = result
+        .overflows
+        .iter()
+        .map(|overflow| {
+            json!({
+                "pc": overflow.pc,
+                "opcode": opcode_name(overflow.opcode),
+                "model": overflow.model.as_ref().map(model_to_json),
+            })
+        })
+        .collect();
+
+    json!({
+        "branches": branches,
+        "constraints": constraints,
+        "status": match result.result {
+            DLResult::Sat => "sat",
+            DLResult::Unsat => "unsat",
+            DLResult::Unknown => "unknown",
+        },
+        "model": model,
+        "transaction": transaction,
+        "replay": result.replay.as_ref().map(|replay| format!("{:?}", replay)),
+        "failure": classify(result).map(|failure| failure.to_string()),
+        "overflows": overflows,
+        "selector": result.selector.map(|selector| format!("0x{}", hex::encode(selector))),
+        "termination": {
+            "opcode": opcode_name(result.halt_opcode),
+            "pc": result.halt_pc,
+            "halt": result.halt.as_ref().map(|halt| format!("{:?}", halt)),
+        },
+    })
+}
+
+/// One JSON record per line, one line per explored path
+pub fn to_json(results: &[PathResult], abi: Option<&Abi>) -> String {
+    results
+        .iter()
+        .map(|result| path_to_json(result, abi).to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// JSON record describing a concrete execution, with words as hex strings
+pub fn execution_to_json(result: &ExecutionResult) -> Value {
+    let word = |value: &U256| json!(format!("{:#x}", value));
+
+    let trace: Vec<Value> = result
+        .trace
+        .iter()
+        .map(|step| {
+            json!({
+                "pc": step.pc,
+                "opcode": opcode_name(step.opcode),
+                "stack": step.stack.iter().map(word).collect::<Vec<Value>>(),
+            })
+        })
+        .collect();
+
+    let storage_writes: Vec<Value> = result
+        .storage_writes
+        .iter()
+        .map(|(key, value)| json!({ "slot": word(key), "value": word(value) }))
+        .collect();
+
+    let logs: Vec<Value> = result
+        .logs
+        .iter()
+        .map(|log| {
+            json!({
+                "topics": log.topics.iter().map(word).collect::<Vec<Value>>(),
+                "data": format!("0x{}", hex::encode(&log.data)),
+            })
+        })
+        .collect();
+
+    json!({
+        "trace": trace,
+        "return_data": format!("0x{}", hex::encode(&result.return_data)),
+        "storage_writes": storage_writes,
+        "logs": logs,
+        "success": result.success(),
+        "termination": {
+            "opcode": opcode_name(result.halt_opcode),
+            "pc": result.halt_pc,
+            "halt": result.halt.as_ref().map(|halt| format!("{:?}", halt)),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitblast::BvSolver;
+    use crate::concrete::{ConcreteEvm, ConcreteInput};
+    use crate::explorer::Explorer;
+    use crate::handlers::{sym_handlers, EvmContext};
+    use crate::hardfork::Hardfork;
+    use ruint::aliases::U256;
+
+    fn explore(code: &str) -> Vec<PathResult> {
+        let code = hex::decode(code).unwrap();
+        let mut explorer = Explorer::new(sym_handlers(Hardfork::default()));
+        explorer.solver = Box::new(BvSolver::new(256));
+        explorer.run(&mut EvmContext::new(code));
+        explorer.results
+    }
+
+    #[test]
+    fn models_keep_full_words_as_decimal_strings() {
+        let model = Model::from([("CALLVALUE".to_string(), U256::MAX)]);
+        assert_eq!(
+            model_to_json(&model),
+            json!({ "CALLVALUE": U256::MAX.to_string() })
+        );
+    }
+
+    #[test]
+    fn paths_record_their_branches_and_termination() {
+        // if (10 > calldataload(0)) jump
+        let results = explore("600035600a11600a57005b00");
+        let records: Vec<Value> = to_json(&results, None)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+
+        let taken = records
+            .iter()
+            .find(|record| record["branches"][0]["taken"] == json!(true))
+            .unwrap();
+        assert_eq!(taken["status"], "sat");
+        assert_eq!(taken["branches"][0]["pc"], 8);
+        assert!(!taken["constraints"].as_array().unwrap().is_empty());
+        assert_eq!(taken["termination"]["opcode"], "STOP");
+        assert_eq!(taken["termination"]["pc"], 11);
+        assert_eq!(taken["termination"]["halt"], Value::Null);
+    }
+
+    #[test]
+    fn exceptional_halts_are_recorded() {
+        // jump(0), which is no JUMPDEST
+        let results = explore("600056");
+        let record = path_to_json(&results[0], None);
+        assert_eq!(record["termination"]["opcode"], "JUMP");
+        assert_eq!(record["termination"]["halt"], "InvalidJump(0)");
+    }
+
+    #[test]
+    fn paths_carry_the_transaction_that_takes_them() {
+        // if (calldataload(0) == 10) jump
+        let results = explore("600035600a14600a57005b00");
+        let records: Vec<Value> = to_json(&results, None)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+
+        let taken = records
+            .iter()
+            .find(|record| record["branches"][0]["taken"] == json!(true))
+            .unwrap();
+        assert_eq!(taken["status"], "sat");
+        assert_eq!(taken["branches"][0]["pc"], 8);
+        assert_eq!(taken["termination"]["opcode"], "STOP");
+        assert_eq!(taken["termination"]["pc"], 11);
+        assert_eq!(taken["failure"], Value::Null);
+        assert_eq!(taken["transaction"]["calldata"], format!("0x{:064x}", 10));
+    }
+
+    #[test]
+    fn failing_paths_name_their_failure() {
+        let results = explore("fe");
+        let record = path_to_json(&results[0], None);
+        assert_eq!(record["failure"], "INVALID");
+        assert_eq!(record["termination"]["opcode"], "INVALID");
+    }
+
+    #[test]
+    fn executions_report_trace_storage_and_outcome() {
+        // sstore(0, 1)
+        let code = hex::decode("600160005500").unwrap();
+        let evm = ConcreteEvm::new(sym_handlers(Hardfork::default()));
+        let record = execution_to_json(&evm.execute(&code, &ConcreteInput::default()));
+
+        assert_eq!(record["success"], true);
+        assert_eq!(
+            record["storage_writes"],
+            json!([{ "slot": "0x0", "value": "0x1" }])
+        );
+        assert_eq!(record["trace"].as_array().unwrap().len(), 4);
+        assert_eq!(
+            record["trace"][2],
+            json!({ "pc": 4, "opcode": "SSTORE", "stack": ["0x1", "0x0"] })
+        );
+        assert_eq!(record["return_data"], "0x");
+        assert_eq!(record["termination"]["opcode"], "STOP");
+    }
+}
@@ -0,0 +1,231 @@
+use serde_json::{json, Value};
+
+use crate::abi::Abi;
+use crate::findings::{Finding, Level, Rule};
+use crate::report::{model_to_json, transaction_to_json};
+use crate::sourcemap::{line_column, SourceMap};
+
+/// Where findings are located: the bytecode, and optionally the sources it was compiled from
+pub struct Artifacts<'a> {
+    /// Path of the runtime binary
+    pub binary: &'a str,
+    /// Runtime bytecode
+    pub code: &'a [u8],
+    /// Source map of the runtime bytecode
+    pub source_map: Option<&'a SourceMap>,
+    /// (path, contents) of each source file, indexed like the source map file ids
+    pub sources: &'a [(String, String)],
+    /// ABI to complete and decode witness calldata with
+    pub abi: Option<&'a Abi>,
+}
+
+/// SARIF 2.1.0 log with one result per finding
+pub fn to_sarif(findings: &[Finding], artifacts: &Artifacts) -> Value {
+    let mut rules: Vec<Rule> = findings.iter().map(|finding| finding.rule).collect();
+    rules.sort();
+    rules.dedup();
+
+    let rule_descriptors: Vec<Value> = rules
+        .iter()
+        .map(|rule| {
+            json!({
+                "id": rule.id(),
+                "shortDescription": { "text": rule.description() },
+            })
+        })
+        .collect();
+
+    let results: Vec<Value> = findings
+        .iter()
+        .map(|finding| {
+            let mut result = json!({
+                "ruleId": finding.rule.id(),
+                "ruleIndex": rules.iter().position(|rule| *rule == finding.rule),
+                "level": match finding.rule.level() {
+                    Level::Error => "error",
+                    Level::Warning => "warning",
+                    Level::Note => "note",
+                },
+                "message": { "text": finding.message },
+                "locations": [location(finding.pc, artifacts)],
+                "properties": { "pc": finding.pc },
+            });
+            if let Some(witness) = &finding.witness {
+                result["properties"]["witness"] = model_to_json(witness);
+            }
+            if let Some(transaction) = &finding.transaction {
+                let mut transaction = transaction.clone();
+                if let Some(abi) = artifacts.abi {
+                    transaction.complete(abi);
+                }
+                let calldata = &transaction.calldata;
+                result["properties"]["calldata"] = json!(format!("0x{}", hex::encode(calldata)));
+                if let Some(call) = artifacts.abi.and_then(|abi| abi.decode_call(calldata)) {
+                    result["properties"]["call"] = json!(call);
+                }
+                result["properties"]["transaction"] = transaction_to_json(&transaction);
+            }
+            if !finding.related.is_empty() {
+                let related: Vec<Value> = finding
+                    .related
+                    .iter()
+                    .enumerate()
+                    .map(|(id, (pc, role))| {
+                        let mut related = location(*pc, artifacts);
+                        related["id"] = json!(id);
+                        related["message"] = json!({ "text": role });
+                        related
+                    })
+                    .collect();
+                result["relatedLocations"] = json!(related);
+            }
+            if !finding.condition.is_empty() {
+                let condition: Vec<Value> = finding
+                    .condition
+                    .iter()
+                    .map(|branch| {
+                        json!({
+                            "pc": branch.pc,
+                            "taken": branch.taken,
+                            "condition": branch.condition.to_string(),
+                        })
+                    })
+                    .collect();
+                result["properties"]["pathCondition"] = json!(condition);
+            }
+            result
+        })
+        .collect();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rule_descriptors,
+                }
+            },
+            "results": results,
+        }]
+    })
+}
+
+// Source location when the source map covers the pc, bytecode address otherwise
+fn location(pc: usize, artifacts: &Artifacts) -> Value {
+    let source = artifacts
+        .source_map
+        .and_then(|map| map.range_for_pc(artifacts.code, pc))
+        .and_then(|range| {
+            let (path, contents) = artifacts.sources.get(range.file as usize)?;
+            Some((path, contents, range))
+        });
+
+    if let Some((path, contents, range)) = source {
+        let (start_line, start_column) = line_column(contents, range.offset);
+        let (end_line, end_column) = line_column(contents, range.offset + range.length);
+        return json!({
+            "physicalLocation": {
+                "artifactLocation": { "uri": path },
+                "region": {
+                    "startLine": start_line,
+                    "startColumn": start_column,
+                    "endLine": end_line,
+                    "endColumn": end_column,
+                },
+            },
+            "logicalLocations": [{ "name": format!("pc {:#x}", pc), "kind": "instruction" }],
+        });
+    }
+
+    json!({
+        "physicalLocation": {
+            "artifactLocation": { "uri": artifacts.binary },
+            "address": { "absoluteAddress": pc, "kind": "instruction" },
+        },
+        "logicalLocations": [{ "name": format!("pc {:#x}", pc), "kind": "instruction" }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finding(rule: Rule, pc: usize) -> Finding {
+        Finding {
+            rule,
+            pc,
+            message: format!("at {}", pc),
+            witness: None,
+            transaction: None,
+            condition: Vec::new(),
+            related: Vec::new(),
+        }
+    }
+
+    // PUSH1 0x00, PUSH1 0x00, REVERT
+    const CODE: [u8; 5] = [0x60, 0x00, 0x60, 0x00, 0xfd];
+
+    #[test]
+    fn findings_share_rule_descriptors() {
+        let findings = [
+            finding(Rule::ReachableRevert, 4),
+            finding(Rule::AssertionFailure, 2),
+            finding(Rule::ReachableRevert, 0),
+        ];
+        let artifacts = Artifacts {
+            binary: "a.bin",
+            code: &CODE,
+            source_map: None,
+            sources: &[],
+            abi: None,
+        };
+        let log = to_sarif(&findings, &artifacts);
+        let run = &log["runs"][0];
+        assert_eq!(log["version"], "2.1.0");
+        assert_eq!(run["tool"]["driver"]["rules"].as_array().unwrap().len(), 2);
+
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0]["ruleId"], "reachable-revert");
+        assert_eq!(results[0]["level"], "note");
+        assert_eq!(results[0]["ruleIndex"], results[2]["ruleIndex"]);
+        assert_eq!(results[1]["level"], "error");
+        assert_eq!(
+            results[0]["locations"][0]["physicalLocation"],
+            json!({
+                "artifactLocation": { "uri": "a.bin" },
+                "address": { "absoluteAddress": 4, "kind": "instruction" },
+            })
+        );
+    }
+
+    #[test]
+    fn source_maps_place_findings_in_the_sources() {
+        let map = SourceMap::parse("0:1:0;;4:6:0").unwrap();
+        let sources = [("C.sol".to_string(), "a\nb\nrevert();\n".to_string())];
+        let artifacts = Artifacts {
+            binary: "a.bin",
+            code: &CODE,
+            source_map: Some(&map),
+            sources: &sources,
+            abi: None,
+        };
+        let mut revert = finding(Rule::ReachableRevert, 4);
+        revert.related.push((0, "pushes the size".to_string()));
+        let log = to_sarif(&[revert], &artifacts);
+        let result = &log["runs"][0]["results"][0];
+        assert_eq!(
+            result["locations"][0]["physicalLocation"],
+            json!({
+                "artifactLocation": { "uri": "C.sol" },
+                "region": { "startLine": 3, "startColumn": 1, "endLine": 3, "endColumn": 7 },
+            })
+        );
+        let related = &result["relatedLocations"][0];
+        assert_eq!(related["message"]["text"], "pushes the size");
+        assert_eq!(related["physicalLocation"]["region"]["startLine"], 1);
+    }
+}
@@ -0,0 +1,497 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// A literal: variable index times two, plus one when negated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Lit(u32);
+
+impl Lit {
+    pub fn new(var: usize, negated: bool) -> Self {
+        Lit((var as u32) << 1 | negated as u32)
+    }
+
+    pub fn var(self) -> usize {
+        (self.0 >> 1) as usize
+    }
+
+    pub fn is_negated(self) -> bool {
+        self.0 & 1 == 1
+    }
+
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl std::ops::Not for Lit {
+    type Output = Lit;
+
+    fn not(self) -> Lit {
+        Lit(self.0 ^ 1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SatResult {
+    Sat,
+    Unsat,
+    /// The conflict budget ran out
+    Unknown,
+}
+
+// Decision heap entry, ordered by activity
+#[derive(Debug, PartialEq)]
+struct Candidate(f64, usize);
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then(other.1.cmp(&self.1))
+    }
+}
+
+/// Conflict driven clause learning SAT solver with two watched literals, first UIP learning,
+/// VSIDS decisions, phase saving and geometric restarts
+#[derive(Debug, Default)]
+pub struct SatSolver {
+    clauses: Vec<Vec<Lit>>,
+    /// Clauses watching each literal, indexed by literal
+    watches: Vec<Vec<usize>>,
+    /// Current value of each variable
+    assigns: Vec<Option<bool>>,
+    /// Decision level each variable was assigned at
+    level: Vec<usize>,
+    /// Clause that implied each variable, None for decisions
+    reason: Vec<Option<usize>>,
+    /// Value each variable had last, reused when deciding on it again
+    phase: Vec<bool>,
+    activity: Vec<f64>,
+    var_inc: f64,
+    heap: BinaryHeap<Candidate>,
+    trail: Vec<Lit>,
+    /// Trail length at the start of each decision level
+    trail_lim: Vec<usize>,
+    /// Next trail entry to propagate
+    qhead: usize,
+    /// Set once an empty clause has been derived
+    unsat: bool,
+    /// Give up with Unknown after this many conflicts
+    pub max_conflicts: Option<u64>,
+}
+
+impl SatSolver {
+    pub fn new() -> Self {
+        Self {
+            var_inc: 1.0,
+            ..Default::default()
+        }
+    }
+
+    /// Allocate a fresh variable
+    pub fn new_var(&mut self) -> usize {
+        let var = self.assigns.len();
+        self.assigns.push(None);
+        self.level.push(0);
+        self.reason.push(None);
+        self.phase.push(false);
+        self.activity.push(0.0);
+        self.watches.push(Vec::new());
+        self.watches.push(Vec::new());
+        self.heap.push(Candidate(0.0, var));
+        var
+    }
+
+    pub fn num_vars(&self) -> usize {
+        self.assigns.len()
+    }
+
+    /// Value of a literal under the current assignment
+    pub fn value(&self, lit: Lit) -> Option<bool> {
+        self.assigns[lit.var()].map(|value| value != lit.is_negated())
+    }
+
+    fn decision_level(&self) -> usize {
+        self.trail_lim.len()
+    }
+
+    /// Add a clause. Must be called before solving, at decision level zero.
+    pub fn add_clause(&mut self, lits: &[Lit]) {
+        if self.unsat {
+            return;
+        }
+
+        let mut clause: Vec<Lit> = Vec::with_capacity(lits.len());
+        for &lit in lits {
+            match self.value(lit) {
+                Some(true) => return,
+                Some(false) => continue,
+                None => {
+                    if clause.contains(&!lit) {
+                        return;
+                    }
+                    if !clause.contains(&lit) {
+                        clause.push(lit);
+                    }
+                }
+            }
+        }
+
+        match clause.len() {
+            0 => self.unsat = true,
+            1 => {
+                self.enqueue(clause[0], None);
+                if self.propagate().is_some() {
+                    self.unsat = true;
+                }
+            }
+            _ => {
+                self.attach(clause);
+            }
+        }
+    }
+
+    fn attach(&mut self, clause: Vec<Lit>) -> usize {
+        let index = self.clauses.len();
+        self.watches[clause[0].index()].push(index);
+        self.watches[clause[1].index()].push(index);
+        self.clauses.push(clause);
+        index
+    }
+
+    fn enqueue(&mut self, lit: Lit, reason: Option<usize>) {
+        let var = lit.var();
+        self.assigns[var] = Some(!lit.is_negated());
+        self.level[var] = self.decision_level();
+        self.reason[var] = reason;
+        self.trail.push(lit);
+    }
+
+    /// Unit propagation, returns a conflicting clause if there is one
+    fn propagate(&mut self) -> Option<usize> {
+        while self.qhead < self.trail.len() {
+            let false_lit = !self.trail[self.qhead];
+            self.qhead += 1;
+
+            let watchers = std::mem::take(&mut self.watches[false_lit.index()]);
+            let mut kept: Vec<usize> = Vec::with_capacity(watchers.len());
+            let mut conflict = None;
+
+            for (i, &ci) in watchers.iter().enumerate() {
+                if conflict.is_some() {
+                    kept.extend_from_slice(&watchers[i..]);
+                    break;
+                }
+
+                // Keep the false literal in the second watch slot
+                if self.clauses[ci][0] == false_lit {
+                    self.clauses[ci].swap(0, 1);
+                }
+                let first = self.clauses[ci][0];
+                if self.value(first) == Some(true) {
+                    kept.push(ci);
+                    continue;
+                }
+
+                // Look for another literal to watch
+                let replacement = (2..self.clauses[ci].len())
+                    .find(|&k| self.value(self.clauses[ci][k]) != Some(false));
+                if let Some(k) = replacement {
+                    self.clauses[ci].swap(1, k);
+                    let lit = self.clauses[ci][1];
+                    self.watches[lit.index()].push(ci);
+                    continue;
+                }
+
+                kept.push(ci);
+                if self.value(first) == Some(false) {
+                    conflict = Some(ci);
+                } else {
+                    self.enqueue(first, Some(ci));
+                }
+            }
+
+            self.watches[false_lit.index()] = kept;
+            if conflict.is_some() {
+                self.qhead = self.trail.len();
+                return conflict;
+            }
+        }
+        None
+    }
+
+    fn bump(&mut self, var: usize) {
+        self.activity[var] += self.var_inc;
+        if self.activity[var] > 1e100 {
+            for activity in self.activity.iter_mut() {
+                *activity *= 1e-100;
+            }
+            self.var_inc *= 1e-100;
+            self.heap = (0..self.num_vars())
+                .map(|v| Candidate(self.activity[v], v))
+                .collect();
+        }
+        self.heap.push(Candidate(self.activity[var], var));
+    }
+
+    /// First UIP conflict analysis, returns the learnt clause and the level to backjump to
+    fn analyze(&mut self, mut conflict: usize) -> (Vec<Lit>, usize) {
+        let mut seen = vec![false; self.num_vars()];
+        let mut learnt: Vec<Lit> = vec![Lit(0)];
+        let mut pending = 0;
+        let mut index = self.trail.len();
+        let mut uip: Option<Lit> = None;
+
+        loop {
+            // The implied literal sits first in its reason clause and is skipped
+            let skip = usize::from(uip.is_some());
+            for k in skip..self.clauses[conflict].len() {
+                let q = self.clauses[conflict][k];
+                let var = q.var();
+                if !seen[var] && self.level[var] > 0 {
+                    seen[var] = true;
+                    self.bump(var);
+                    if self.level[var] == self.decision_level() {
+                        pending += 1;
+                    } else {
+                        learnt.push(q);
+                    }
+                }
+            }
+
+            // Walk back to the next literal of the conflict on the trail
+            loop {
+                index -= 1;
+                if seen[self.trail[index].var()] {
+                    break;
+                }
+            }
+            let p = self.trail[index];
+            seen[p.var()] = false;
+            pending -= 1;
+            uip = Some(p);
+            if pending == 0 {
+                break;
+            }
+            conflict = self.reason[p.var()].expect("implied literal has a reason");
+        }
+        learnt[0] = !uip.unwrap();
+
+        // Backjump to the second highest level in the clause, watched in slot 1
+        let mut backjump = 0;
+        if learnt.len() > 1 {
+            let mut max_i = 1;
+            for i in 2..learnt.len() {
+                if self.level[learnt[i].var()] > self.level[learnt[max_i].var()] {
+                    max_i = i;
+                }
+            }
+            learnt.swap(1, max_i);
+            backjump = self.level[learnt[1].var()];
+        }
+        (learnt, backjump)
+    }
+
+    fn cancel_until(&mut self, level: usize) {
+        if self.decision_level() <= level {
+            return;
+        }
+        let start = self.trail_lim[level];
+        for i in (start..self.trail.len()).rev() {
+            let var = self.trail[i].var();
+            self.phase[var] = self.assigns[var].unwrap_or(false);
+            self.assigns[var] = None;
+            self.reason[var] = None;
+            self.heap.push(Candidate(self.activity[var], var));
+        }
+        self.trail.truncate(start);
+        self.trail_lim.truncate(level);
+        self.qhead = start;
+    }
+
+    fn pick_branch_var(&mut self) -> Option<usize> {
+        while let Some(Candidate(_, var)) = self.heap.pop() {
+            if self.assigns[var].is_none() {
+                return Some(var);
+            }
+        }
+        None
+    }
+
+    /// Search for a satisfying assignment of the clauses
+    pub fn solve(&mut self) -> SatResult {
+        if self.unsat || self.propagate().is_some() {
+            self.unsat = true;
+            return SatResult::Unsat;
+        }
+
+        let mut conflicts: u64 = 0;
+        let mut restart_limit = 100.0;
+        let mut since_restart = 0;
+
+        loop {
+            if let Some(conflict) = self.propagate() {
+                conflicts += 1;
+                since_restart += 1;
+                if self.decision_level() == 0 {
+                    self.unsat = true;
+                    return SatResult::Unsat;
+                }
+                if self.max_conflicts.is_some_and(|max| conflicts > max) {
+                    self.cancel_until(0);
+                    return SatResult::Unknown;
+                }
+
+                let (learnt, backjump) = self.analyze(conflict);
+                self.cancel_until(backjump);
+                if learnt.len() == 1 {
+                    self.enqueue(learnt[0], None);
+                } else {
+                    let asserting = learnt[0];
+                    let index = self.attach(learnt);
+                    self.enqueue(asserting, Some(index));
+                }
+                self.var_inc /= 0.95;
+            } else {
+                if since_restart as f64 >= restart_limit {
+                    since_restart = 0;
+                    restart_limit *= 1.5;
+                    self.cancel_until(0);
+                }
+
+                match self.pick_branch_var() {
+                    None => return SatResult::Sat,
+                    Some(var) => {
+                        self.trail_lim.push(self.trail.len());
+                        let lit = Lit::new(var, !self.phase[var]);
+                        self.enqueue(lit, None);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Deterministic xorshift, so failures reproduce
+    fn next(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    fn random_cnf(state: &mut u64, vars: usize, clauses: usize) -> Vec<Vec<Lit>> {
+        (0..clauses)
+            .map(|_| {
+                (0..3)
+                    .map(|_| {
+                        let var = next(state) as usize % vars;
+                        Lit::new(var, next(state) & 1 == 1)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn satisfies(cnf: &[Vec<Lit>], value: impl Fn(Lit) -> bool) -> bool {
+        cnf.iter()
+            .all(|clause| clause.iter().any(|&lit| value(lit)))
+    }
+
+    fn brute_force(cnf: &[Vec<Lit>], vars: usize) -> bool {
+        (0..1u32 << vars)
+            .any(|bits| satisfies(cnf, |lit| (bits >> lit.var() & 1 == 1) != lit.is_negated()))
+    }
+
+    #[test]
+    fn random_formulas_agree_with_brute_force() {
+        let mut state = 0x9e3779b97f4a7c15;
+        let vars = 10;
+        let (mut sat, mut unsat) = (0, 0);
+        // Around 4.3 clauses per variable, where formulas go from mostly sat to mostly unsat
+        for clauses in [30, 40, 43, 50, 60] {
+            for _ in 0..60 {
+                let cnf = random_cnf(&mut state, vars, clauses);
+                let mut solver = SatSolver::new();
+                for _ in 0..vars {
+                    solver.new_var();
+                }
+                for clause in &cnf {
+                    solver.add_clause(clause);
+                }
+
+                let expected = brute_force(&cnf, vars);
+                match solver.solve() {
+                    SatResult::Sat => {
+                        assert!(expected, "{:?} is unsat", cnf);
+                        assert!(satisfies(&cnf, |lit| solver.value(lit) == Some(true)));
+                        sat += 1;
+                    }
+                    SatResult::Unsat => {
+                        assert!(!expected, "{:?} is sat", cnf);
+                        unsat += 1;
+                    }
+                    SatResult::Unknown => panic!("no conflict budget was set"),
+                }
+            }
+        }
+        assert!(sat > 0 && unsat > 0);
+    }
+
+    // Every pigeon in a hole, no two pigeons sharing one
+    fn pigeonhole(solver: &mut SatSolver, pigeons: usize, holes: usize) {
+        let lit = |pigeon: usize, hole: usize| Lit::new(pigeon * holes + hole, false);
+        for _ in 0..pigeons * holes {
+            solver.new_var();
+        }
+        for pigeon in 0..pigeons {
+            let clause: Vec<Lit> = (0..holes).map(|hole| lit(pigeon, hole)).collect();
+            solver.add_clause(&clause);
+        }
+        for hole in 0..holes {
+            for a in 0..pigeons {
+                for b in a + 1..pigeons {
+                    solver.add_clause(&[!lit(a, hole), !lit(b, hole)]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pigeons_do_not_fit_in_fewer_holes() {
+        let mut solver = SatSolver::new();
+        pigeonhole(&mut solver, 5, 4);
+        assert_eq!(solver.solve(), SatResult::Unsat);
+
+        let mut solver = SatSolver::new();
+        pigeonhole(&mut solver, 4, 4);
+        assert_eq!(solver.solve(), SatResult::Sat);
+    }
+
+    #[test]
+    fn the_conflict_budget_gives_up() {
+        let mut solver = SatSolver::new();
+        solver.max_conflicts = Some(1);
+        pigeonhole(&mut solver, 7, 6);
+        assert_eq!(solver.solve(), SatResult::Unknown);
+    }
+
+    #[test]
+    fn contradicting_units_are_unsat() {
+        let mut solver = SatSolver::new();
+        let var = solver.new_var();
+        solver.add_clause(&[Lit::new(var, false)]);
+        solver.add_clause(&[Lit::new(var, true)]);
+        assert_eq!(solver.solve(), SatResult::Unsat);
+    }
+}
@@ -0,0 +1,269 @@
+use anyhow::{anyhow, Result};
+use ruint::aliases::U256;
+use std::collections::BTreeSet;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use crate::handlers::Branch;
+use crate::smtlib::{encode_branch, Declarations};
+use crate::solve::{DLResult, Model, Solver};
+
+/// Solver backend that talks SMT-LIB2 to an external solver process over stdin/stdout, checking
+/// the full QF_BV encoding of each condition. Once the process fails to take a command or answer
+/// one in time, every check is Unknown.
+pub struct SmtProcessSolver {
+    child: Child,
+    stdin: ChildStdin,
+    /// Lines of the process's output, read on a separate thread so waiting on them can time out
+    lines: Receiver<String>,
+    /// How long to wait for an answer before giving up on the process
+    pub timeout: Duration,
+    /// Constants declared in each open scope, the outermost first
+    declared: Vec<BTreeSet<String>>,
+    /// Whether talking to the process has failed
+    broken: bool,
+}
+
+impl SmtProcessSolver {
+    /// Start the solver from a command line such as `z3 -in`
+    pub fn new(command: &str) -> Result<Self> {
+        let mut parts = command.split_whitespace();
+        let program = parts
+            .next()
+            .ok_or_else(|| anyhow!("Empty solver command"))?;
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| anyhow!("Could not start solver {}: {}", program, e))?;
+
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow!("No solver stdin"))?;
+        let mut stdout = BufReader::new(
+            child
+                .stdout
+                .take()
+                .ok_or_else(|| anyhow!("No solver stdout"))?,
+        );
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || loop {
+            let mut line = String::new();
+            match stdout.read_line(&mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    if sender.send(line).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        let mut solver = Self {
+            child,
+            stdin,
+            lines,
+            timeout: Duration::from_secs(60),
+            declared: vec![BTreeSet::new()],
+            broken: false,
+        };
+        solver.send("(set-option :print-success false)");
+        solver.send("(set-option :produce-models true)");
+        solver.send("(set-logic QF_BV)");
+        Ok(solver)
+    }
+
+    fn send(&mut self, command: &str) {
+        if self.broken {
+            return;
+        }
+        if writeln!(self.stdin, "{}", command)
+            .and_then(|_| self.stdin.flush())
+            .is_err()
+        {
+            self.broken = true;
+        }
+    }
+
+    /// Read one complete response, which may span several lines when it is an s-expression. None
+    /// when the process has failed, closed its output or not answered within the timeout; a
+    /// process that times out is killed.
+    fn read_response(&mut self) -> Option<String> {
+        let mut response = String::new();
+        while !self.broken {
+            match self.lines.recv_timeout(self.timeout) {
+                Err(_) => {
+                    self.broken = true;
+                    let _ = self.child.kill();
+                }
+                Ok(line) => {
+                    response.push_str(&line);
+                    if !response.trim().is_empty() && balanced(&response) {
+                        return Some(response.trim().to_string());
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn is_declared(&self, name: &str) -> bool {
+        self.declared.iter().any(|scope| scope.contains(name))
+    }
+}
+
+impl Solver for SmtProcessSolver {
+    fn assert_branch(&mut self, branch: &Branch) {
+        let mut decls = Declarations::default();
+        let assertion = encode_branch(branch, &mut decls);
+        for name in decls.names {
+            if !self.is_declared(&name) {
+                self.send(&format!("(declare-const {} (_ BitVec 256))", name));
+                self.declared.last_mut().unwrap().insert(name);
+            }
+        }
+        self.send(&format!("(assert {})", assertion));
+    }
+
+    fn check(&mut self) -> DLResult {
+        self.send("(check-sat)");
+        match self.read_response().as_deref() {
+            Some("sat") => DLResult::Sat,
+            Some("unsat") => DLResult::Unsat,
+            _ => DLResult::Unknown,
+        }
+    }
+
+    fn model(&mut self) -> Option<Model> {
+        let names: Vec<String> = self.declared.iter().flatten().cloned().collect();
+        if names.is_empty() {
+            return Some(Model::new());
+        }
+
+        self.send(&format!("(get-value ({}))", names.join(" ")));
+        let response = self.read_response()?;
+        parse_values(&response)
+    }
+
+    fn push(&mut self) {
+        self.declared.push(BTreeSet::new());
+        self.send("(push 1)");
+    }
+
+    fn pop(&mut self) {
+        self.declared.pop();
+        self.send("(pop 1)");
+    }
+}
+
+impl Drop for SmtProcessSolver {
+    fn drop(&mut self) {
+        let _ = writeln!(self.stdin, "(exit)");
+        let _ = self.child.wait();
+    }
+}
+
+// Whether every parenthesis outside of quoted symbols is closed
+fn balanced(text: &str) -> bool {
+    let mut depth = 0i64;
+    let mut quoted = false;
+    for c in text.chars() {
+        match c {
+            '|' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth -= 1,
+            _ => {}
+        }
+    }
+    depth <= 0 && !quoted
+}
+
+/// Parse a `get-value` response of the form `((|name| #x..) (|name| (_ bv.. 256)) ...)`
+fn parse_values(response: &str) -> Option<Model> {
+    let tokens = tokenize(response);
+    let mut model = Model::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        if tokens[i].starts_with('|') {
+            let name = tokens[i].trim_matches('|').to_string();
+            let (value, used) = parse_bv(&tokens[i + 1..])?;
+            model.insert(name, value);
+            i += used + 1;
+        } else {
+            i += 1;
+        }
+    }
+    Some(model)
+}
+
+fn parse_bv(tokens: &[String]) -> Option<(U256, usize)> {
+    let first = tokens.first()?;
+    if let Some(hex) = first.strip_prefix("#x") {
+        return Some((U256::from_str_radix(hex, 16).ok()?, 1));
+    }
+    if let Some(bin) = first.strip_prefix("#b") {
+        return Some((U256::from_str_radix(bin, 2).ok()?, 1));
+    }
+    // (_ bvN 256)
+    if first == "(" && tokens.get(1)? == "_" {
+        let value = tokens.get(2)?.strip_prefix("bv")?;
+        return Some((U256::from_str_radix(value, 10).ok()?, 5));
+    }
+    None
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            '(' | ')' => {
+                tokens.push(c.to_string());
+                chars.next();
+            }
+            '|' => {
+                let mut symbol = String::from(chars.next().unwrap());
+                for c in chars.by_ref() {
+                    symbol.push(c);
+                    if c == '|' {
+                        break;
+                    }
+                }
+                tokens.push(symbol);
+            }
+            _ if c.is_whitespace() => {
+                chars.next();
+            }
+            _ => {
+                let mut atom = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+                    atom.push(c);
+                    chars.next();
+                }
+                tokens.push(atom);
+            }
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_parse_in_every_notation() {
+        let model = parse_values("((|a| #x0f) (|b| #b101) (|c| (_ bv42 256)))").unwrap();
+        assert_eq!(model["a"], U256::from(15));
+        assert_eq!(model["b"], U256::from(5));
+        assert_eq!(model["c"], U256::from(42));
+    }
+}
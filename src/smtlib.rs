@@ -0,0 +1,251 @@
+use std::collections::BTreeSet;
+
+use crate::explorer::PathResult;
+use crate::handlers::Branch;
+use crate::opcodes::*;
+use crate::sym_stack::{Kind, Term};
+
+const ZERO: &str = "(_ bv0 256)";
+const ONE: &str = "(_ bv1 256)";
+
+/// Free 256-bit constants referenced by encoded terms
+#[derive(Debug, Clone, Default)]
+pub struct Declarations {
+    pub names: BTreeSet<String>,
+}
+
+impl Declarations {
+    /// `declare-const` commands for every constant
+    pub fn to_smtlib(&self) -> String {
+        self.names
+            .iter()
+            .map(|name| format!("(declare-const {} (_ BitVec 256))\n", name))
+            .collect()
+    }
+}
+
+/// Encode a term as a 256-bit vector expression. Terms we have no bit-vector semantics for, such as
+/// CALLDATALOAD or SLOAD, become free constants named after the term itself, so structurally equal
+/// terms share a constant.
+pub fn encode_term(term: &Term, decls: &mut Declarations) -> String {
+    if term.sym_val.kind == Kind::Concrete {
+        return format!("(_ bv{} 256)", term.sym_val.value);
+    }
+
+    let opcode = term.sym_val.value as u8;
+    let args: Vec<String> = term
+        .args
+        .iter()
+        .map(|arg| encode_term(arg, decls))
+        .collect();
+    let bool_to_bv = |cond: String| format!("(ite {} {} {})", cond, ONE, ZERO);
+    // EVM division and modulo by zero are zero, SMT-LIB leaves them as all ones / the dividend
+    let guard_zero =
+        |divisor: &str, op: String| format!("(ite (= {} {}) {} {})", divisor, ZERO, ZERO, op);
+    let wide = |arg: &str| format!("((_ zero_extend 256) {})", arg);
+
+    match (opcode, args.as_slice()) {
+        (ADD, [a, b]) => format!("(bvadd {} {})", a, b),
+        (MUL, [a, b]) => format!("(bvmul {} {})", a, b),
+        (SUB, [a, b]) => format!("(bvsub {} {})", a, b),
+        (DIV, [a, b]) => guard_zero(b, format!("(bvudiv {} {})", a, b)),
+        (SDIV, [a, b]) => guard_zero(b, format!("(bvsdiv {} {})", a, b)),
+        (MOD, [a, b]) => guard_zero(b, format!("(bvurem {} {})", a, b)),
+        (SMOD, [a, b]) => guard_zero(b, format!("(bvsrem {} {})", a, b)),
+        (ADDMOD, [a, b, n]) => guard_zero(
+            n,
+            format!(
+                "((_ extract 255 0) (bvurem (bvadd {} {}) {}))",
+                wide(a),
+                wide(b),
+                wide(n)
+            ),
+        ),
+        (MULMOD, [a, b, n]) => guard_zero(
+            n,
+            format!(
+                "((_ extract 255 0) (bvurem (bvmul {} {}) {}))",
+                wide(a),
+                wide(b),
+                wide(n)
+            ),
+        ),
+        (SIGNEXTEND, [_, x]) if term.args[0].sym_val.kind == Kind::Concrete => {
+            let bytes = term.args[0].sym_val.value;
+            if bytes >= 31 {
+                x.clone()
+            } else {
+                let bits = 8 * (bytes + 1);
+                format!(
+                    "((_ sign_extend {}) ((_ extract {} 0) {}))",
+                    256 - bits,
+                    bits - 1,
+                    x
+                )
+            }
+        }
+        (LT, [a, b]) => bool_to_bv(format!("(bvult {} {})", a, b)),
+        (GT, [a, b]) => bool_to_bv(format!("(bvugt {} {})", a, b)),
+        (SLT, [a, b]) => bool_to_bv(format!("(bvslt {} {})", a, b)),
+        (SGT, [a, b]) => bool_to_bv(format!("(bvsgt {} {})", a, b)),
+        (EQ, [a, b]) => bool_to_bv(format!("(= {} {})", a, b)),
+        (ISZERO, [a]) => bool_to_bv(format!("(= {} {})", a, ZERO)),
+        (AND, [a, b]) => format!("(bvand {} {})", a, b),
+        (OR, [a, b]) => format!("(bvor {} {})", a, b),
+        (XOR, [a, b]) => format!("(bvxor {} {})", a, b),
+        (NOT, [a]) => format!("(bvnot {})", a),
+        (BYTE, [i, x]) => format!(
+            "(ite (bvult {i} (_ bv32 256)) (bvand (bvlshr {x} (bvmul (bvsub (_ bv31 256) {i}) (_ bv8 256))) (_ bv255 256)) {zero})",
+            i = i,
+            x = x,
+            zero = ZERO
+        ),
+        // The shift amount is on top of the stack, the value below it
+        (SHL, [shift, value]) => format!("(bvshl {} {})", value, shift),
+        (SHR, [shift, value]) => format!("(bvlshr {} {})", value, shift),
+        (SAR, [shift, value]) => format!("(bvashr {} {})", value, shift),
+        _ => {
+            let name = format!("|{}|", term);
+            decls.names.insert(name.clone());
+            name
+        }
+    }
+}
+
+/// Encode the direction taken at a JUMPI as a boolean
+pub fn encode_branch(branch: &Branch, decls: &mut Declarations) -> String {
+    let condition = encode_term(&branch.condition, decls);
+    if branch.taken {
+        format!("(not (= {} {}))", condition, ZERO)
+    } else {
+        format!("(= {} {})", condition, ZERO)
+    }
+}
+
+/// Standalone QF_BV script checking the condition of a path
+pub fn path_script(result: &PathResult) -> String {
+    let mut decls = Declarations::default();
+    let mut asserts = String::new();
+    for branch in &result.branches {
+        // Concrete conditions were decided while exploring and carry no information
+        if branch.condition.sym_val.kind == Kind::Concrete {
+            continue;
+        }
+        asserts.push_str(&format!(
+            "; JUMPI @ {:#x} {}\n(assert {})\n",
+            branch.pc,
+            if branch.taken { "taken" } else { "not taken" },
+            encode_branch(branch, &mut decls)
+        ));
+    }
+
+    format!(
+        "; path ending in {} @ {:#x}\n(set-logic QF_BV)\n{}{}(check-sat)\n(get-model)\n",
+        opcode_name(result.halt_opcode),
+        result.halt_pc,
+        decls.to_smtlib(),
+        asserts
+    )
+}
+
+/// Scripts for every path, separated by `(reset)` so they can be fed to a solver in one go
+pub fn to_smtlib(results: &[PathResult]) -> String {
+    results
+        .iter()
+        .map(path_script)
+        .collect::<Vec<String>>()
+        .join("(reset)\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::explorer::Explorer;
+    use crate::handlers::{sym_handlers, EvmContext};
+    use crate::hardfork::Hardfork;
+    use crate::sym_stack::{Kind, SymVal};
+
+    fn constant(value: u64) -> Term {
+        Term {
+            sym_val: SymVal {
+                value,
+                kind: Kind::Concrete,
+            },
+            args: Vec::new(),
+        }
+    }
+
+    fn op(opcode: u8, args: Vec<Term>) -> Term {
+        Term {
+            sym_val: SymVal {
+                value: opcode as u64,
+                kind: Kind::Symbolic,
+            },
+            args,
+        }
+    }
+
+    fn input() -> Term {
+        op(CALLDATALOAD, vec![constant(0)])
+    }
+
+    #[test]
+    fn opaque_terms_become_shared_constants() {
+        let mut decls = Declarations::default();
+        let sum = op(ADD, vec![input(), input()]);
+        let name = format!("|{}|", input());
+        assert_eq!(
+            encode_term(&sum, &mut decls),
+            format!("(bvadd {} {})", name, name)
+        );
+        assert_eq!(
+            decls.to_smtlib(),
+            format!("(declare-const {} (_ BitVec 256))\n", name)
+        );
+    }
+
+    #[test]
+    fn evm_semantics_are_kept() {
+        let mut decls = Declarations::default();
+        let shift = op(SHR, vec![constant(224), input()]);
+        let name = format!("|{}|", input());
+        assert_eq!(
+            encode_term(&shift, &mut decls),
+            format!("(bvlshr {} (_ bv224 256))", name)
+        );
+        let division = op(DIV, vec![constant(1), input()]);
+        assert_eq!(
+            encode_term(&division, &mut decls),
+            format!(
+                "(ite (= {n} {z}) {z} (bvudiv (_ bv1 256) {n}))",
+                n = name,
+                z = ZERO
+            )
+        );
+        let byte = op(SIGNEXTEND, vec![constant(0), input()]);
+        assert_eq!(
+            encode_term(&byte, &mut decls),
+            format!("((_ sign_extend 248) ((_ extract 7 0) {}))", name)
+        );
+    }
+
+    #[test]
+    fn scripts_assert_each_symbolic_branch() {
+        // if (calldataload(0) == 10) jump
+        let code = hex::decode("600035600a14600a57005b00").unwrap();
+        let mut explorer = Explorer::new(sym_handlers(Hardfork::default()));
+        explorer.run(&mut EvmContext::new(code));
+        let taken = explorer
+            .results
+            .iter()
+            .find(|result| result.branches[0].taken)
+            .unwrap();
+
+        let script = path_script(taken);
+        assert!(script.starts_with("; path ending in STOP @ 0xb\n(set-logic QF_BV)\n"));
+        assert!(script.contains("; JUMPI @ 0x8 taken\n(assert (not (= (ite (= "));
+        assert_eq!(script.matches("declare-const").count(), 1);
+        assert!(script.ends_with("(check-sat)\n(get-model)\n"));
+        assert_eq!(to_smtlib(&explorer.results).matches("(reset)\n").count(), 1);
+    }
+}
@@ -1,18 +1,128 @@
-use crate::sym_stack::Expr;
+use ruint::aliases::U256;
 use std::cmp;
+use std::collections::BTreeMap;
 
-#[derive(Debug)]
+use crate::handlers::Branch;
+use crate::opcodes::*;
+use crate::sym_stack::{is_concrete, is_symbolic, Constant, Expr, Kind, SymVal, Term, Variable};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum DLResult {
     Sat,
     Unsat,
+    /// The backend could not decide
+    Unknown,
+}
+
+/// Satisfying assignment, keyed by the name of each free variable
+pub type Model = BTreeMap<String, U256>;
+
+/// A decision procedure for path conditions, with incremental scopes
+pub trait Solver {
+    /// Assert the direction taken at a JUMPI in the current scope
+    fn assert_branch(&mut self, branch: &Branch);
+    /// Check whether everything asserted so far is satisfiable
+    fn check(&mut self) -> DLResult;
+    /// Assignment satisfying the assertions, after a Sat check
+    fn model(&mut self) -> Option<Model>;
+    /// Open a new scope
+    fn push(&mut self);
+    /// Drop everything asserted since the matching push
+    fn pop(&mut self);
+}
+
+/// Difference logic solver: conditions are encoded as a - b <= k constraints and checked for
+/// negative cycles with Bellman-Ford. Conditions it cannot encode are left out.
+#[derive(Debug, Default)]
+pub struct DlSolver {
+    constraints: Vec<Expr>,
+    /// Number of constraints when each open scope was pushed
+    scopes: Vec<usize>,
+}
+
+impl Solver for DlSolver {
+    fn assert_branch(&mut self, branch: &Branch) {
+        self.constraints.extend(branch_to_expressions(branch));
+    }
+
+    fn check(&mut self) -> DLResult {
+        if self.constraints.is_empty() {
+            return DLResult::Sat;
+        }
+        solve(&self.constraints)
+    }
+
+    fn model(&mut self) -> Option<Model> {
+        model(&self.constraints)
+    }
+
+    fn push(&mut self) {
+        self.scopes.push(self.constraints.len());
+    }
+
+    fn pop(&mut self) {
+        let len = self.scopes.pop().expect("pop without a matching push");
+        self.constraints.truncate(len);
+    }
+}
+
+/// DL constraints for the direction taken at a JUMPI
+pub fn branch_to_expressions(branch: &Branch) -> Vec<Expr> {
+    let condition_opcode: u8 = branch.condition.sym_val.value as u8;
+    if is_concrete(&branch.condition)
+        || !(is_relational(condition_opcode) || condition_opcode == ISZERO)
+    {
+        return vec![];
+    }
+
+    let expression = term_to_expression(branch.condition.clone());
+    if branch.taken {
+        expression
+    } else {
+        negate(&expression)
+    }
 }
 
 pub fn solve(exprs: &[Expr]) -> DLResult {
-    let n_exprs = exprs.len();
-    if n_exprs == 0 {
+    if exprs.is_empty() {
         panic!("Empty expression list");
     }
 
+    match shortest_paths(exprs) {
+        Some(_) => DLResult::Sat,
+        None => DLResult::Unsat,
+    }
+}
+
+/// Solve the constraints and, when they are satisfiable, return an assignment for them
+pub fn model(exprs: &[Expr]) -> Option<Model> {
+    if exprs.is_empty() {
+        return Some(Model::new());
+    }
+
+    // x = -dist satisfies every a - b <= k, shift it so that variable 0 is zero. DL works over the
+    // integers, so a value can come out negative, which no word can hold. It is stored as its
+    // two's complement, and such a model does not satisfy the path over words.
+    let dist = shortest_paths(exprs)?;
+    let mut model = Model::new();
+    for expr in exprs {
+        for var in [expr.a.0, expr.b.0] {
+            if var != 0 {
+                let value = dist[0] - dist[var as usize];
+                let word = if value < 0 {
+                    U256::from(value.unsigned_abs()).wrapping_neg()
+                } else {
+                    U256::from(value as u64)
+                };
+                model.insert(Variable(var).to_string(), word);
+            }
+        }
+    }
+    Some(model)
+}
+
+// Bellman-Ford over the constraint graph, returns None when there is a negative cycle
+fn shortest_paths(exprs: &[Expr]) -> Option<Vec<i64>> {
     // Figure out how many nodes we have
     let mut max_var = cmp::max(exprs[0].a.0, exprs[0].b.0);
     for expr in exprs.iter().skip(1) {
@@ -40,19 +150,228 @@ pub fn solve(exprs: &[Expr]) -> DLResult {
     }
 
     // 2. Negative cycle detection
-    let mut has_negative_cycle = false;
     for expr in exprs {
         let u = expr.a.0 as usize;
         let v = expr.b.0 as usize;
         if dist[v] > dist[u] + adj[u][v] {
-            has_negative_cycle = true;
-            break;
+            return None;
         }
     }
 
-    if has_negative_cycle {
-        DLResult::Unsat
-    } else {
-        DLResult::Sat
+    Some(dist)
+}
+
+// Negate a single DL expression: !(a - b <= k) is b - a <= -k - 1. Conjunctions would need a
+// disjunction, which DL cannot express, so those are dropped.
+fn negate(exprs: &[Expr]) -> Vec<Expr> {
+    match exprs {
+        [expr] => vec![Expr {
+            a: expr.b.clone(),
+            b: expr.a.clone(),
+            k: Constant(-expr.k.0 - 1),
+        }],
+        _ => vec![],
+    }
+}
+
+// A condition that never holds, 0 - 0 <= -1
+fn never() -> Vec<Expr> {
+    vec![Expr {
+        a: Variable(0),
+        b: Variable(0),
+        k: Constant(-1),
+    }]
+}
+
+// Encode symbolic terms into DL expressions, when possible: LT, GT, EQ and the negation of a
+// comparison by ISZERO
+fn term_to_expression(term: Term) -> Vec<Expr> {
+    let opcode: u8 = term.sym_val.value as u8;
+    let args = term.args;
+
+    if opcode == LT {
+        if is_symbolic(&args[0]) && is_symbolic(&args[1]) {
+            return vec![Expr {
+                a: Variable(args[0].sym_val.value),
+                b: Variable(args[1].sym_val.value),
+                k: Constant(-1),
+            }];
+        } else if is_symbolic(&args[0]) && is_concrete(&args[1]) {
+            // Nothing is below zero
+            let Some(bound) = args[1].sym_val.value.checked_sub(1) else {
+                return never();
+            };
+            return match i64::try_from(bound) {
+                Ok(k) => vec![Expr {
+                    a: Variable(args[0].sym_val.value),
+                    b: Variable(0),
+                    k: Constant(k),
+                }],
+                Err(_) => vec![],
+            };
+        } else if is_concrete(&args[0]) && is_symbolic(&args[1]) {
+            return match i64::try_from(args[0].sym_val.value) {
+                Ok(k) if k < i64::MAX => vec![Expr {
+                    a: Variable(0),
+                    b: Variable(args[1].sym_val.value),
+                    k: Constant(-(k + 1)),
+                }],
+                _ => vec![],
+            };
+        } else if args[0].sym_val.value < args[1].sym_val.value {
+            return vec![];
+        } else {
+            return never();
+        }
+    }
+
+    if opcode == GT {
+        let swapped_term = Term {
+            sym_val: SymVal {
+                value: LT as u64,
+                kind: Kind::Symbolic,
+            },
+            args: vec![args[1].clone(), args[0].clone()],
+        };
+        return term_to_expression(swapped_term);
+    }
+
+    if opcode == EQ {
+        // A constant side is a distance from zero, not a variable
+        let side = |term: &Term| match is_concrete(term) {
+            true => i64::try_from(term.sym_val.value)
+                .ok()
+                .map(|k| (Variable(0), k)),
+            false => Some((Variable(term.sym_val.value), 0)),
+        };
+        let (Some((a, ka)), Some((b, kb))) = (side(&args[0]), side(&args[1])) else {
+            return vec![];
+        };
+        return vec![
+            Expr {
+                a: a.clone(),
+                b: b.clone(),
+                k: Constant(kb - ka),
+            },
+            Expr {
+                a: b,
+                b: a,
+                k: Constant(ka - kb),
+            },
+        ];
+    }
+
+    if opcode == ISZERO && is_symbolic(&args[0]) {
+        let arg_opcode: u8 = args[0].sym_val.value as u8;
+        if arg_opcode == LT || arg_opcode == GT {
+            // negate child expressions
+            let child = term_to_expression(args[0].to_owned());
+            return negate(&child);
+        }
+    }
+
+    vec![]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sym_stack::{Kind, SymVal};
+
+    fn constant(value: u64) -> Term {
+        Term {
+            sym_val: SymVal {
+                value,
+                kind: Kind::Concrete,
+            },
+            args: Vec::new(),
+        }
+    }
+
+    fn op(opcode: u8, args: Vec<Term>) -> Term {
+        Term {
+            sym_val: SymVal {
+                value: opcode as u64,
+                kind: Kind::Symbolic,
+            },
+            args,
+        }
+    }
+
+    fn input() -> Term {
+        op(CALLDATALOAD, vec![constant(0)])
+    }
+
+    fn check(conditions: &[(Term, bool)]) -> DLResult {
+        let mut solver = DlSolver::default();
+        for (condition, taken) in conditions {
+            solver.assert_branch(&Branch {
+                pc: 0,
+                taken: *taken,
+                condition: condition.clone(),
+            });
+        }
+        solver.check()
+    }
+
+    #[test]
+    fn nothing_is_below_zero() {
+        let below_zero = Term::op(LT, vec![input(), Term::constant(0)]);
+        assert_eq!(check(&[(below_zero.clone(), true)]), DLResult::Unsat);
+        assert_eq!(check(&[(below_zero.clone(), false)]), DLResult::Sat);
+        let above_zero = Term::op(ISZERO, vec![below_zero]);
+        assert_eq!(check(&[(above_zero, true)]), DLResult::Sat);
+    }
+
+    #[test]
+    fn bounds_on_one_value_must_overlap() {
+        let below = op(LT, vec![input(), constant(5)]);
+        let above = op(GT, vec![input(), constant(10)]);
+        assert_eq!(check(&[(below.clone(), true)]), DLResult::Sat);
+        assert_eq!(
+            check(&[(below.clone(), true), (above.clone(), true)]),
+            DLResult::Unsat
+        );
+        assert_eq!(check(&[(below, false), (above, true)]), DLResult::Sat);
+    }
+
+    #[test]
+    fn constants_below_a_value_bound_it_from_below() {
+        // 100 < x and x < 60 cannot both hold
+        let above = op(LT, vec![constant(100), input()]);
+        let below = op(LT, vec![input(), constant(60)]);
+        assert_eq!(check(&[(above.clone(), true)]), DLResult::Sat);
+        assert_eq!(check(&[(above, true), (below, true)]), DLResult::Unsat);
+    }
+
+    #[test]
+    fn iszero_negates_comparisons_exactly() {
+        // !(x < 5) leaves x == 5 possible, and nothing below it
+        let below = op(ISZERO, vec![op(LT, vec![input(), constant(5)])]);
+        let up_to_five = op(LT, vec![input(), constant(6)]);
+        let up_to_four = op(LT, vec![input(), constant(5)]);
+        assert_eq!(
+            check(&[(below.clone(), true), (up_to_five, true)]),
+            DLResult::Sat
+        );
+        assert_eq!(check(&[(below, true), (up_to_four, true)]), DLResult::Unsat);
+    }
+
+    #[test]
+    fn equalities_past_the_difference_range_are_left_out() {
+        let equal = |value: u64| op(EQ, vec![input(), constant(value)]);
+        assert_eq!(term_to_expression(equal(i64::MAX as u64)).len(), 2);
+        assert!(term_to_expression(equal(u64::MAX)).is_empty());
+    }
+
+    #[test]
+    fn bounds_past_the_difference_range_are_left_out() {
+        let wide = Term::op(LT, vec![Term::constant(u64::MAX), input()]);
+        assert!(branch_to_expressions(&Branch {
+            pc: 0,
+            taken: true,
+            condition: wide,
+        })
+        .is_empty());
     }
 }
@@ -0,0 +1,108 @@
+use anyhow::{anyhow, Result};
+
+use crate::cfg::instructions;
+
+/// Source range of one instruction, as in a solc source map entry
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SourceRange {
+    /// Byte offset into the source file
+    pub offset: usize,
+    /// Length of the range in bytes
+    pub length: usize,
+    /// Index of the source file, -1 for code solc generated without a source
+    pub file: i64,
+}
+
+/// A solc source map: one entry per instruction, in instruction order
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    pub entries: Vec<SourceRange>,
+}
+
+impl SourceMap {
+    /// Parse the compressed `s:l:f:j:m;...` format. Empty fields repeat the previous entry.
+    pub fn parse(map: &str) -> Result<Self> {
+        let mut entries = Vec::new();
+        let mut current = SourceRange::default();
+
+        for entry in map.trim().split(';') {
+            let fields: Vec<&str> = entry.split(':').collect();
+            if let Some(offset) = fields.first().filter(|f| !f.is_empty()) {
+                current.offset = offset
+                    .parse()
+                    .map_err(|_| anyhow!("Bad source map offset {}", offset))?;
+            }
+            if let Some(length) = fields.get(1).filter(|f| !f.is_empty()) {
+                current.length = length
+                    .parse()
+                    .map_err(|_| anyhow!("Bad source map length {}", length))?;
+            }
+            if let Some(file) = fields.get(2).filter(|f| !f.is_empty()) {
+                current.file = file
+                    .parse()
+                    .map_err(|_| anyhow!("Bad source map file index {}", file))?;
+            }
+            entries.push(current.clone());
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// Source range of the instruction at `pc`
+    pub fn range_for_pc(&self, code: &[u8], pc: usize) -> Option<&SourceRange> {
+        let index = instructions(code).iter().position(|(p, _)| *p == pc)?;
+        self.entries.get(index).filter(|range| range.file >= 0)
+    }
+}
+
+/// 1-based (line, column) of a byte offset in a source file
+pub fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source.as_bytes()[..offset.min(source.len())];
+    let line = before.iter().filter(|&&b| b == b'\n').count() + 1;
+    let line_start = before
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    (line, before.len() - line_start + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_fields_repeat_the_previous_entry() {
+        let map = SourceMap::parse("0:10:0;;2:3;:4::j;5:1:-1").unwrap();
+        let ranges: Vec<(usize, usize, i64)> = map
+            .entries
+            .iter()
+            .map(|range| (range.offset, range.length, range.file))
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![(0, 10, 0), (0, 10, 0), (2, 3, 0), (2, 4, 0), (5, 1, -1)]
+        );
+        assert!(SourceMap::parse("0:x:0").is_err());
+    }
+
+    #[test]
+    fn pcs_map_through_push_data() {
+        // PUSH2 0x0001, PUSH1 0x00, STOP
+        let code = [0x61, 0x00, 0x01, 0x60, 0x00, 0x00];
+        let map = SourceMap::parse("1:1:0;2:1:0;3:1:-1").unwrap();
+        assert_eq!(map.range_for_pc(&code, 3).unwrap().offset, 2);
+        // Inside push data, and generated code
+        assert_eq!(map.range_for_pc(&code, 1), None);
+        assert_eq!(map.range_for_pc(&code, 5), None);
+    }
+
+    #[test]
+    fn offsets_become_lines_and_columns() {
+        let source = "ab\ncd\n";
+        assert_eq!(line_column(source, 0), (1, 1));
+        assert_eq!(line_column(source, 4), (2, 2));
+        assert_eq!(line_column(source, 6), (3, 1));
+        assert_eq!(line_column(source, 100), (3, 1));
+    }
+}
@@ -0,0 +1,203 @@
+use crate::opcodes::*;
+use crate::sym_stack::{is_concrete, Term};
+
+/// Symbolic key-value storage. Writes are kept in order; a load looks back through them for the
+/// latest write to the same slot, as long as every write since is provably to another slot.
+#[derive(Default, Debug, Clone)]
+pub struct SymStorage {
+    pub writes: Vec<(Term, Term)>,
+}
+
+/// What the writes so far say about a slot
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lookup {
+    /// The value last written to the slot
+    Written(Term),
+    /// No write went to the slot, it holds what it held before
+    Unwritten,
+    /// A write may or may not have gone to the slot, depending on the inputs
+    Unknown,
+}
+
+impl SymStorage {
+    /// What the slot `key` holds after the writes so far
+    pub fn load(&self, key: &Term) -> Lookup {
+        for (written, value) in self.writes.iter().rev() {
+            match same_slot(written, key) {
+                Some(true) => return Lookup::Written(value.clone()),
+                Some(false) => {}
+                None => return Lookup::Unknown,
+            }
+        }
+        Lookup::Unwritten
+    }
+
+    /// Write `value` to `key`
+    pub fn store(&mut self, key: Term, value: Term) {
+        self.writes.push((key, value));
+    }
+
+    /// A load of `key` by `opcode` that stands for none of the values written so far, for slots
+    /// whose lookup is unknown. It is tagged with the number of writes, so loads of the same slot
+    /// separated by writes are told apart.
+    pub fn fresh(&self, opcode: u8, key: Term) -> Term {
+        Term::op(opcode, vec![key, Term::constant(self.writes.len() as u64)])
+    }
+}
+
+/// Whether two keys are the same slot: Some(true) when they are, Some(false) when they cannot be,
+/// and None when it depends on the inputs. Hashes are taken to be collision free and to never land
+/// on a constant, as every Solidity storage layout assumes.
+pub fn same_slot(a: &Term, b: &Term) -> Option<bool> {
+    if a == b {
+        return Some(true);
+    }
+    if is_concrete(a) && is_concrete(b) {
+        return Some(false);
+    }
+
+    // Slots at fixed distances from the same base, like the fields of a struct
+    let (base_a, offset_a) = split_offset(a);
+    let (base_b, offset_b) = split_offset(b);
+    if base_a == base_b {
+        return Some(offset_a == offset_b);
+    }
+
+    let is_hash = |term: &Term| !is_concrete(term) && term.sym_val.value as u8 == SHA3;
+    match (hashed_words(base_a), hashed_words(base_b)) {
+        (Some(x), Some(y)) if x.len() != y.len() => Some(false),
+        (Some(x), Some(y)) => x
+            .iter()
+            .zip(y)
+            .any(|(x, y)| same_slot(x, y) == Some(false))
+            .then_some(false),
+        _ if is_hash(base_a) && is_concrete(b) || is_hash(base_b) && is_concrete(a) => Some(false),
+        _ => None,
+    }
+}
+
+// A key as a base plus a constant offset, `ADD(base, c)` or the key itself plus 0
+fn split_offset(term: &Term) -> (&Term, u64) {
+    if !is_concrete(term) && term.sym_val.value as u8 == ADD {
+        match term.args.as_slice() {
+            [base, offset] | [offset, base] if is_concrete(offset) && !is_concrete(base) => {
+                return (base, offset.sym_val.value)
+            }
+            _ => {}
+        }
+    }
+    (term, 0)
+}
+
+/// The hash of the words `data`, whose terms stand for the bytes hashed, so that hashes of equal
+/// data are equal terms: `SHA3(size, word, ...)`
+pub fn hash_of(data: Vec<Term>) -> Term {
+    let size = Term::constant(32 * data.len() as u64);
+    Term::op(SHA3, [size].into_iter().chain(data).collect())
+}
+
+/// A hash of data we cannot follow, unlike any other hash: `SHA3(tag, offset, size)`. The tag is
+/// odd, which no size of hashed words is, and `step` makes it unique on the path.
+pub fn opaque_hash(offset: Term, size: Term, step: usize) -> Term {
+    Term::op(
+        SHA3,
+        vec![Term::constant(2 * step as u64 + 1), offset, size],
+    )
+}
+
+// Words a hash built by `hash_of` was taken over
+fn hashed_words(term: &Term) -> Option<&[Term]> {
+    match term.args.split_first() {
+        Some((size, words))
+            if !is_concrete(term)
+                && term.sym_val.value as u8 == SHA3
+                && is_concrete(size)
+                && size.sym_val.value == 32 * words.len() as u64 =>
+        {
+            Some(words)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(key: Term, slot: u64) -> Term {
+        hash_of(vec![key, Term::constant(slot)])
+    }
+
+    #[test]
+    fn constant_slots_are_equal_or_distinct() {
+        assert_eq!(
+            same_slot(&Term::constant(1), &Term::constant(1)),
+            Some(true)
+        );
+        assert_eq!(
+            same_slot(&Term::constant(1), &Term::constant(2)),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn mapping_slots_compare_by_key_and_slot() {
+        let caller = Term::op(CALLER, Vec::new());
+        let input = Term::op(CALLDATALOAD, vec![Term::constant(0)]);
+        assert_eq!(
+            same_slot(&mapping(caller.clone(), 0), &mapping(caller.clone(), 0)),
+            Some(true)
+        );
+        assert_eq!(
+            same_slot(&mapping(caller.clone(), 0), &mapping(input.clone(), 1)),
+            Some(false)
+        );
+        assert_eq!(
+            same_slot(&mapping(caller.clone(), 0), &mapping(input, 0)),
+            None
+        );
+        assert_eq!(
+            same_slot(&mapping(caller, 0), &Term::constant(0)),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn offsets_from_one_base_are_distinct() {
+        let base = mapping(Term::op(CALLER, Vec::new()), 0);
+        let field = Term::op(ADD, vec![Term::constant(1), base.clone()]);
+        assert_eq!(same_slot(&base, &field), Some(false));
+        assert_eq!(
+            same_slot(&field, &Term::op(ADD, vec![base, Term::constant(1)])),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn opaque_hashes_are_unknown() {
+        let size = Term::op(CALLDATASIZE, Vec::new());
+        let first = opaque_hash(Term::constant(0), size.clone(), 3);
+        let second = opaque_hash(Term::constant(0), size, 7);
+        assert_eq!(same_slot(&first, &first), Some(true));
+        assert_eq!(same_slot(&first, &second), None);
+        assert_eq!(same_slot(&first, &Term::constant(0x40)), Some(false));
+    }
+
+    #[test]
+    fn loads_only_see_writes_to_the_same_slot() {
+        let caller = mapping(Term::op(CALLER, Vec::new()), 0);
+        let input = mapping(Term::op(CALLDATALOAD, vec![Term::constant(0)]), 0);
+        let mut storage = SymStorage::default();
+        storage.store(Term::constant(1), Term::constant(5));
+        storage.store(caller.clone(), Term::constant(1));
+
+        assert_eq!(storage.load(&caller), Lookup::Written(Term::constant(1)));
+        assert_eq!(storage.load(&input), Lookup::Unknown);
+        assert_eq!(
+            storage.load(&Term::constant(1)),
+            Lookup::Written(Term::constant(5))
+        );
+        // Every write is provably to another slot
+        assert_eq!(storage.load(&Term::constant(2)), Lookup::Unwritten);
+    }
+}
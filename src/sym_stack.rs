@@ -1,22 +1,93 @@
+use ruint::aliases::U256;
+use std::fmt;
+
+use crate::opcodes::{opcode_name, OR, SHL};
+
 // Symbolic or Concrete Value
-#[derive(Clone, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct Term {
     pub sym_val: SymVal,
     pub args: Vec<Term>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct SymVal {
     pub value: u64,
     pub kind: Kind,
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub enum Kind {
     Concrete,
     Symbolic,
 }
 
+impl Term {
+    /// A known value
+    pub fn constant(value: u64) -> Self {
+        Self {
+            sym_val: SymVal {
+                value,
+                kind: Kind::Concrete,
+            },
+            args: Vec::new(),
+        }
+    }
+
+    /// A known word. Concrete terms hold 64 bits, so wider words are built from their 64-bit limbs
+    /// with SHL and OR, which keeps their exact value for the solvers and for folding.
+    pub fn word(value: U256) -> Self {
+        if let Ok(value) = u64::try_from(value) {
+            return Self::constant(value);
+        }
+        value
+            .as_limbs()
+            .iter()
+            .enumerate()
+            .filter(|&(_, &limb)| limb != 0)
+            .map(|(i, &limb)| match i {
+                0 => Self::constant(limb),
+                _ => Self::op(
+                    SHL,
+                    vec![Self::constant(64 * i as u64), Self::constant(limb)],
+                ),
+            })
+            .reduce(|low, high| Self::op(OR, vec![high, low]))
+            .unwrap_or_else(|| Self::constant(0))
+    }
+
+    /// The value `opcode` computes from `args`, given with the top of the stack first
+    pub fn op(opcode: u8, args: Vec<Term>) -> Self {
+        Self {
+            sym_val: SymVal {
+                value: opcode as u64,
+                kind: Kind::Symbolic,
+            },
+            args,
+        }
+    }
+}
+
+pub fn is_symbolic(term: &Term) -> bool {
+    term.sym_val.kind == Kind::Symbolic
+}
+
+pub fn is_concrete(term: &Term) -> bool {
+    term.sym_val.kind == Kind::Concrete
+}
+
+/// Whether `part` appears anywhere in `term`
+pub fn contains(term: &Term, part: &Term) -> bool {
+    term == part || term.args.iter().any(|arg| contains(arg, part))
+}
+
+/// Whether the value of `term` depends on a value produced by `opcode`
+pub fn mentions(term: &Term, opcode: u8) -> bool {
+    is_symbolic(term)
+        && (term.sym_val.value == opcode as u64
+            || term.args.iter().any(|arg| mentions(arg, opcode)))
+}
+
 // An actual integer constant
 #[derive(Debug, Default, Clone)]
 pub struct Constant(pub i64);
@@ -32,6 +103,38 @@ pub struct Expr {
     pub k: Constant,
 }
 
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.sym_val.kind == Kind::Concrete {
+            return write!(f, "{:#x}", self.sym_val.value);
+        }
+
+        write!(f, "{}", opcode_name(self.sym_val.value as u8))?;
+        if !self.args.is_empty() {
+            let args: Vec<String> = self.args.iter().map(|arg| arg.to_string()).collect();
+            write!(f, "({})", args.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+// Variable 0 is the constant zero, the others are named after the opcode that produced them
+impl fmt::Display for Variable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 == 0 {
+            write!(f, "0")
+        } else {
+            write!(f, "{}", opcode_name(self.0 as u8))
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} - {} <= {}", self.a, self.b, self.k.0)
+    }
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct EvmSymStack {
     pub values: Vec<Term>,
@@ -108,3 +211,25 @@ impl EvmSymStack {
         self.values[swap_idx] = value1;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concrete::evaluate_term;
+
+    #[test]
+    fn words_keep_their_value() {
+        let values = [
+            U256::ZERO,
+            U256::from(u64::MAX),
+            U256::from(1) << 72,
+            U256::MAX,
+            U256::from(0xffu64) << 224,
+        ];
+        for value in values {
+            assert_eq!(evaluate_term(&Term::word(value)), Some(value));
+        }
+        assert!(is_concrete(&Term::word(U256::from(7))));
+        assert!(is_symbolic(&Term::word(U256::from(1) << 64)));
+    }
+}
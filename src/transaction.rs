@@ -0,0 +1,286 @@
+use ruint::aliases::U256;
+use std::collections::BTreeMap;
+
+use crate::abi::Abi;
+use crate::explorer::PathResult;
+use crate::opcodes::*;
+use crate::replay::{conditions_to_input, env_field, set_selector};
+use crate::solve::Model;
+use crate::sym_stack::{mentions, Term};
+
+/// Environment values a path can read besides the sender and the value sent
+const ENVIRONMENT: [u8; 12] = [
+    ADDRESS,
+    ORIGIN,
+    GASPRICE,
+    COINBASE,
+    TIMESTAMP,
+    NUMBER,
+    DIFFICULTY,
+    GASLIMIT,
+    CHAINID,
+    BASEFEE,
+    BLOBBASEFEE,
+    SELFBALANCE,
+];
+
+/// Ether the sender gets on top of the value, to pay for gas
+const GAS_MONEY: u128 = 100_000_000_000_000_000_000;
+
+/// Placeholder for the address the contract is deployed at on the devnet
+const CONTRACT: &str = "$CONTRACT";
+
+/// A transaction that reproduces a path: the calldata, sender and value under which its condition
+/// holds, with the environment and storage it reads. Anything the path does not read is left out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transaction {
+    pub calldata: Vec<u8>,
+    pub caller: U256,
+    pub value: U256,
+    /// Other environment values the path reads, by the opcode that reads them
+    pub environment: Vec<(u8, U256)>,
+    /// Storage slots the path reads at fixed keys, with the values they must hold beforehand
+    pub storage: BTreeMap<U256, U256>,
+    /// Whether external calls must succeed, when the path looks at their outcome
+    pub calls_succeed: Option<bool>,
+}
+
+impl Transaction {
+    /// Transaction under which the conditions hold according to the model, calling the function
+    /// with `selector` when there is one
+    pub fn new(conditions: &[&Term], model: &Model, selector: Option<[u8; 4]>) -> Self {
+        let mut input = conditions_to_input(conditions.iter().copied(), model);
+        if let Some(selector) = selector {
+            set_selector(&mut input, selector);
+        }
+        let reads = |opcode: u8| {
+            conditions
+                .iter()
+                .any(|condition| mentions(condition, opcode))
+        };
+
+        let environment = ENVIRONMENT
+            .iter()
+            .filter(|&&opcode| reads(opcode))
+            .filter_map(|&opcode| Some((opcode, *env_field(&mut input.env, opcode)?)))
+            .collect();
+        let calls_succeed = [CALL, CALLCODE, DELEGATECALL, STATICCALL]
+            .iter()
+            .any(|&opcode| reads(opcode))
+            .then_some(input.env.call_success);
+        Self {
+            calldata: input.calldata,
+            caller: input.env.caller,
+            value: input.env.callvalue,
+            environment,
+            storage: input.storage,
+            calls_succeed,
+        }
+    }
+
+    /// Transaction that drives execution down a path, according to its model
+    pub fn on_path(result: &PathResult, model: &Model) -> Self {
+        let conditions: Vec<&Term> = result
+            .branches
+            .iter()
+            .map(|branch| &branch.condition)
+            .collect();
+        Self::new(&conditions, model, result.selector)
+    }
+
+    /// Pad the calldata to a complete encoding of the call, when the ABI has its function
+    pub fn complete(&mut self, abi: &Abi) {
+        let selector: Option<[u8; 4]> = self.calldata.get(..4).and_then(|s| s.try_into().ok());
+        if let Some(function) = selector.and_then(|selector| abi.function(selector)) {
+            function.complete(&mut self.calldata);
+        }
+    }
+
+    /// Commands that set up an anvil devnet and send the transaction to the contract deployed at
+    /// `$CONTRACT`. Environment the devnet cannot be told to use is stated in `#` comments.
+    pub fn commands(&self) -> Vec<String> {
+        let mut commands = Vec::new();
+        for (slot, value) in &self.storage {
+            commands.push(format!(
+                "cast rpc anvil_setStorageAt {} {:#x} 0x{}",
+                CONTRACT,
+                slot,
+                hex::encode(value.to_be_bytes::<32>())
+            ));
+        }
+
+        // Solvers treat accounts as full words, which the devnet cannot use
+        let accounts = [(CALLER, self.caller)].into_iter().chain(
+            self.environment
+                .iter()
+                .copied()
+                .filter(|(opcode, _)| matches!(*opcode, ADDRESS | ORIGIN | COINBASE)),
+        );
+        for (opcode, value) in accounts {
+            if value >> 160 != U256::ZERO {
+                commands.push(format!(
+                    "# {} {:#x} is wider than an address, the devnet cannot reproduce it",
+                    opcode_name(opcode),
+                    value
+                ));
+            }
+        }
+
+        let mut gas_price = None;
+        for &(opcode, value) in &self.environment {
+            commands.push(match opcode {
+                ADDRESS => format!("# the contract must be deployed at {}", address(value)),
+                ORIGIN if value == self.caller => continue,
+                ORIGIN => format!(
+                    "# tx.origin must be {}, send through a contract deployed at the sender",
+                    address(value)
+                ),
+                GASPRICE => {
+                    gas_price = Some(value);
+                    continue;
+                }
+                COINBASE => format!("cast rpc anvil_setCoinbase {}", address(value)),
+                TIMESTAMP => format!("cast rpc evm_setNextBlockTimestamp {:#x}", value),
+                GASLIMIT => format!("cast rpc evm_setBlockGasLimit {:#x}", value),
+                CHAINID => format!("cast rpc anvil_setChainId {:#x}", value),
+                BASEFEE => format!("cast rpc anvil_setNextBlockBaseFeePerGas {:#x}", value),
+                SELFBALANCE => format!("cast rpc anvil_setBalance {} {:#x}", CONTRACT, value),
+                _ => format!("# {} must be {:#x}", opcode_name(opcode), value),
+            });
+        }
+        if self.calls_succeed == Some(false) {
+            commands.push("# external calls must fail".to_string());
+        }
+
+        let sender = address(self.caller);
+        commands.push(format!("cast rpc anvil_impersonateAccount {}", sender));
+        commands.push(format!(
+            "cast rpc anvil_setBalance {} {:#x}",
+            sender,
+            self.value.saturating_add(U256::from(GAS_MONEY))
+        ));
+        let mut send = format!(
+            "cast send {} 0x{} --from {} --unlocked",
+            CONTRACT,
+            hex::encode(&self.calldata),
+            sender
+        );
+        if !self.value.is_zero() {
+            send += &format!(" --value {}", self.value);
+        }
+        if let Some(gas_price) = gas_price {
+            send += &format!(" --gas-price {}", gas_price);
+        }
+        commands.push(send);
+        commands
+    }
+}
+
+// An account word as an address, its low 20 bytes
+fn address(value: U256) -> String {
+    format!("0x{}", hex::encode(&value.to_be_bytes::<32>()[12..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(opcode: u8) -> Term {
+        Term::op(opcode, Vec::new())
+    }
+
+    #[test]
+    fn only_what_the_path_reads_is_set() {
+        let caller = read(CALLER);
+        let timestamp = read(TIMESTAMP);
+        let slot = Term::op(SLOAD, vec![Term::constant(1)]);
+        let conditions = [
+            Term::op(EQ, vec![caller.clone(), Term::constant(5)]),
+            Term::op(GT, vec![timestamp.clone(), slot.clone()]),
+        ];
+        let model = Model::from([
+            (caller.to_string(), U256::from(5)),
+            (timestamp.to_string(), U256::from(100)),
+            (slot.to_string(), U256::from(99)),
+            // Read by no condition
+            (read(NUMBER).to_string(), U256::from(3)),
+        ]);
+        let conditions: Vec<&Term> = conditions.iter().collect();
+        let transaction = Transaction::new(&conditions, &model, Some([1, 2, 3, 4]));
+
+        assert_eq!(transaction.calldata[..4], [1, 2, 3, 4]);
+        assert_eq!(transaction.caller, U256::from(5));
+        assert_eq!(transaction.environment, vec![(TIMESTAMP, U256::from(100))]);
+        assert_eq!(
+            transaction.storage,
+            BTreeMap::from([(U256::from(1), U256::from(99))])
+        );
+        assert_eq!(transaction.calls_succeed, None);
+    }
+
+    #[test]
+    fn commands_set_up_the_devnet_and_send() {
+        let transaction = Transaction {
+            calldata: vec![0xaa, 0xbb, 0xcc, 0xdd],
+            caller: U256::from(0x10),
+            value: U256::from(7),
+            environment: vec![
+                (ORIGIN, U256::from(0x10)),
+                (GASPRICE, U256::from(3)),
+                (TIMESTAMP, U256::from(0x100)),
+                (NUMBER, U256::from(9)),
+            ],
+            storage: BTreeMap::from([(U256::from(2), U256::from(1))]),
+            calls_succeed: Some(false),
+        };
+        let sender = "0x0000000000000000000000000000000000000010";
+        assert_eq!(
+            transaction.commands(),
+            vec![
+                format!("cast rpc anvil_setStorageAt $CONTRACT 0x2 0x{:064x}", 1),
+                "cast rpc evm_setNextBlockTimestamp 0x100".to_string(),
+                "# NUMBER must be 0x9".to_string(),
+                "# external calls must fail".to_string(),
+                format!("cast rpc anvil_impersonateAccount {}", sender),
+                format!("cast rpc anvil_setBalance {} {:#x}", sender, GAS_MONEY + 7),
+                format!(
+                    "cast send $CONTRACT 0xaabbccdd --from {} --unlocked --value 7 --gas-price 3",
+                    sender
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn accounts_wider_than_an_address_are_flagged() {
+        let transaction = Transaction {
+            calldata: Vec::new(),
+            caller: U256::from(1) << 160,
+            value: U256::ZERO,
+            environment: vec![(COINBASE, U256::from(2))],
+            storage: BTreeMap::new(),
+            calls_succeed: Some(true),
+        };
+        let commands = transaction.commands();
+        assert!(commands[0].starts_with("# CALLER 0x1000000000000000000000000000000000000000"));
+        assert_eq!(commands.len(), 5);
+        assert!(commands[1].starts_with("cast rpc anvil_setCoinbase 0x00000000"));
+        assert!(!commands[4].contains("--value"));
+    }
+
+    #[test]
+    fn complete_pads_calls_the_abi_knows() {
+        let abi = Abi::parse(
+            r#"[{"type": "function", "name": "set", "inputs": [{"name": "x", "type": "uint256"}]}]"#,
+        )
+        .unwrap();
+        let selector = abi.find("set").unwrap().selector();
+        let mut transaction = Transaction::new(&[], &Model::new(), Some(selector));
+        transaction.complete(&abi);
+        assert_eq!(transaction.calldata.len(), 36);
+
+        let mut unknown = Transaction::new(&[], &Model::new(), Some([0; 4]));
+        unknown.complete(&abi);
+        assert_eq!(unknown.calldata.len(), 4);
+    }
+}
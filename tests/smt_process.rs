@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+use sym_exec::handlers::Branch;
+use sym_exec::opcodes::CALLDATALOAD;
+use sym_exec::smt_process::SmtProcessSolver;
+use sym_exec::solve::{DLResult, Solver};
+use sym_exec::sym_stack::Term;
+
+fn stub(result: &str) -> SmtProcessSolver {
+    let command = format!("{} {}", env!("CARGO_BIN_EXE_stub_solver"), result);
+    SmtProcessSolver::new(&command).unwrap()
+}
+
+fn branch() -> Branch {
+    Branch {
+        pc: 0,
+        taken: true,
+        condition: Term::op(CALLDATALOAD, vec![Term::constant(0)]),
+    }
+}
+
+#[test]
+fn answers_come_from_the_process() {
+    let mut solver = stub("sat");
+    solver.push();
+    solver.assert_branch(&branch());
+    assert_eq!(solver.check(), DLResult::Sat);
+    let model = solver.model().unwrap();
+    assert!(!model.is_empty());
+    assert!(model.values().all(|value| value.is_zero()));
+    solver.pop();
+
+    assert_eq!(stub("unsat").check(), DLResult::Unsat);
+}
+
+#[test]
+fn a_dying_process_is_unknown() {
+    let mut solver = stub("die");
+    solver.assert_branch(&branch());
+    assert_eq!(solver.check(), DLResult::Unknown);
+    assert_eq!(solver.model(), None);
+    solver.push();
+    assert_eq!(solver.check(), DLResult::Unknown);
+    solver.pop();
+}
+
+#[test]
+fn a_process_that_stops_answering_times_out_as_unknown() {
+    let mut solver = stub("hang");
+    solver.timeout = Duration::from_millis(200);
+    solver.assert_branch(&branch());
+    assert_eq!(solver.check(), DLResult::Unknown);
+    assert_eq!(solver.model(), None);
+}